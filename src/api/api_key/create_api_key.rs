@@ -0,0 +1,41 @@
+use crate::api::*;
+use crate::api_key::{ApiKey, Scope};
+use salvo::prelude::*;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct ReqData {
+    name: String,
+    /// 为空时不限制范围
+    #[serde(default)]
+    scopes: Vec<Scope>,
+}
+
+#[derive(Serialize)]
+struct Resp {
+    #[serde(flatten)]
+    info: ApiKey,
+    /// 密钥只在创建时返回，之后无法再次获取
+    key: String,
+}
+
+/// 为当前用户创建 API 密钥，密钥的权限不超过用户的角色
+#[handler]
+pub async fn create_api_key(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<Resp>, Error> {
+    let data: ReqData = req.parse_json().await?;
+    if data.name.is_empty() {
+        return Err(anyhow!("Missing name").into());
+    }
+    let name = CurrentUser::from_depot(depot)?.name.clone();
+    let (key, info) =
+        StateLock::from_depot(depot)?
+            .write()
+            .await
+            .api_keys
+            .create(&name, data.name, data.scopes);
+    save_api_keys(depot).await?;
+    Ok(ApiResponse::ok(Resp { info, key }))
+}
@@ -0,0 +1,16 @@
+use crate::api::*;
+use crate::api_key::ApiKey;
+use salvo::prelude::*;
+
+/// 获取当前用户的 API 密钥，按创建时间排列，不包含密钥本身
+#[handler]
+pub async fn get_api_key_list(depot: &mut Depot) -> Result<ApiResponse<Vec<ApiKey>>, Error> {
+    let name = CurrentUser::from_depot(depot)?.name.clone();
+    Ok(ApiResponse::ok(
+        StateLock::from_depot(depot)?
+            .read()
+            .await
+            .api_keys
+            .list(&name),
+    ))
+}
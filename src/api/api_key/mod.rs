@@ -0,0 +1,3 @@
+pub mod create_api_key;
+pub mod get_api_key_list;
+pub mod revoke_api_key;
@@ -0,0 +1,28 @@
+use crate::api::*;
+use salvo::prelude::*;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct ReqData {
+    id: String,
+}
+
+/// 按密钥标识吊销当前用户的 API 密钥
+#[handler]
+pub async fn revoke_api_key(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<()>, Error> {
+    let data: ReqData = req.parse_json().await?;
+    let name = CurrentUser::from_depot(depot)?.name.clone();
+    if !StateLock::from_depot(depot)?
+        .write()
+        .await
+        .api_keys
+        .revoke(&name, &data.id)
+    {
+        return Err(anyhow!("API key not found").into());
+    }
+    save_api_keys(depot).await?;
+    Ok(ApiResponse::ok(()))
+}
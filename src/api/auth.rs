@@ -1,7 +1,18 @@
 use super::*;
 use salvo::prelude::*;
 
+#[derive(Serialize)]
+struct Resp {
+    username: String,
+    role: Role,
+}
+
+/// 检查登录状态，返回当前用户
 #[handler]
-pub async fn auth() -> Result<ApiResponse<()>, Error> {
-    Ok(ApiResponse::new(Code::Success, (), ""))
+pub async fn auth(depot: &mut Depot) -> Result<ApiResponse<Resp>, Error> {
+    let user = CurrentUser::from_depot(depot)?;
+    Ok(ApiResponse::ok(Resp {
+        username: user.name.clone(),
+        role: user.role,
+    }))
 }
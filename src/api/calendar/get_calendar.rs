@@ -0,0 +1,112 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::api::*;
+use crate::calendar::{predict, series_releases};
+use salvo::prelude::*;
+use serde::Serialize;
+use time::{macros::format_description, OffsetDateTime, UtcOffset};
+
+#[derive(Serialize)]
+struct CalendarEntry {
+    series_id: usize,
+    series_name: String,
+    episode: u32,
+    /// 预计发布时间，Unix 时间戳（秒）
+    expected: u64,
+    late: bool,
+}
+
+#[derive(Serialize)]
+struct CalendarDay {
+    /// `YYYY-MM-DD`，按请求的时区计算
+    date: String,
+    releases: Vec<CalendarEntry>,
+}
+
+#[derive(Serialize)]
+struct Resp {
+    days: Vec<CalendarDay>,
+    /// 超过预计时间仍未发布的集数
+    late: Vec<CalendarEntry>,
+}
+
+/// 允许的最大时区偏移（秒）
+const MAX_UTC_OFFSET: i32 = 14 * 3600;
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 获取放送日历，根据各剧集已发布的集数推断每周放送时间，列出每天预计发布的集数
+///
+/// # 参数
+/// * `days` - 查询参数，从今天起的天数，默认为 7，最多 28
+/// * `utc_offset` - 查询参数，用于划分日期的时区偏移（分钟），默认为 0，范围为 ±14 小时
+#[handler]
+pub async fn get_calendar(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<Resp>, Error> {
+    let days: u32 = req.query::<u32>("days").unwrap_or(7).clamp(1, 28);
+    let offset = req
+        .query::<i32>("utc_offset")
+        .unwrap_or(0)
+        .checked_mul(60)
+        .filter(|secs| secs.abs() <= MAX_UTC_OFFSET)
+        .and_then(|secs| UtcOffset::from_whole_seconds(secs).ok())
+        .ok_or_else(|| Error::bad_request("Invalid utc_offset"))?;
+    let now = SystemTime::now();
+    let today = OffsetDateTime::from(now).to_offset(offset).date();
+    let start: SystemTime = today.midnight().assume_offset(offset).into();
+    let end = start + Duration::from_secs(days as u64 * 24 * 3600);
+
+    let mut calendar: Vec<CalendarDay> = (0..days)
+        .map(|day| CalendarDay {
+            date: (today + time::Duration::days(day as i64))
+                .format(format_description!("[year]-[month]-[day]"))
+                .unwrap_or_default(),
+            releases: Vec::new(),
+        })
+        .collect();
+    let mut late = Vec::new();
+
+    let db = DataBaseLock::from_depot(depot)?.read().await;
+    for series in db.series_list.values() {
+        let Some(prediction) = predict(&series_releases(series, &db.rss_list).await) else {
+            continue;
+        };
+        if !prediction.is_active(now)
+            || series
+                .total_episodes
+                .is_some_and(|total| prediction.next_episode > total)
+        {
+            continue;
+        }
+        let entry = |episode: u32, expected: SystemTime| CalendarEntry {
+            series_id: series.id,
+            series_name: series.name.clone(),
+            episode,
+            expected: unix_secs(expected),
+            late: episode == prediction.next_episode && prediction.is_late(now),
+        };
+        if prediction.is_late(now) {
+            late.push(entry(prediction.next_episode, prediction.expected));
+        }
+        for (episode, expected) in prediction.project(series.total_episodes, start, end) {
+            let day = expected.duration_since(start).unwrap_or_default().as_secs() / (24 * 3600);
+            if let Some(day) = calendar.get_mut(day as usize) {
+                day.releases.push(entry(episode, expected));
+            }
+        }
+    }
+    for day in calendar.iter_mut() {
+        day.releases.sort_by_key(|e| e.expected);
+    }
+    late.sort_by_key(|e| e.expected);
+    Ok(ApiResponse::ok(Resp {
+        days: calendar,
+        late,
+    }))
+}
@@ -0,0 +1,19 @@
+use std::collections::HashMap;
+
+use crate::api::*;
+use crate::state::Category;
+use salvo::prelude::*;
+
+/// 获取所有分类
+#[handler]
+pub async fn get_category_list(
+    depot: &mut Depot,
+) -> Result<ApiResponse<HashMap<String, Category>>, Error> {
+    Ok(ApiResponse::ok(
+        ConfigLock::from_depot(depot)?
+            .read()
+            .await
+            .categories
+            .clone(),
+    ))
+}
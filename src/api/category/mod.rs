@@ -0,0 +1,3 @@
+pub mod get_category_list;
+pub mod remove_category;
+pub mod set_category;
@@ -0,0 +1,38 @@
+use crate::api::*;
+use salvo::prelude::*;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct ReqData {
+    name: String,
+}
+
+/// 删除分类，仍有订阅使用该分类时拒绝删除
+#[handler]
+pub async fn remove_category(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<()>, Error> {
+    let data: ReqData = req.parse_json().await?;
+    let mut used_by = 0;
+    for rss in DataBaseLock::from_depot(depot)?
+        .read()
+        .await
+        .rss_list
+        .values()
+    {
+        if rss.read().await.category.as_ref() == Some(&data.name) {
+            used_by += 1;
+        }
+    }
+    if used_by > 0 {
+        return Err(anyhow!("Category {} is used by {} rss", data.name, used_by).into());
+    }
+    ConfigLock::from_depot(depot)?
+        .write()
+        .await
+        .categories
+        .remove(&data.name)
+        .context("Category")?;
+    Ok(ApiResponse::ok(()))
+}
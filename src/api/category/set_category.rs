@@ -0,0 +1,26 @@
+use crate::api::*;
+use crate::state::Category;
+use salvo::prelude::*;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct ReqData {
+    name: String,
+    category: Category,
+}
+
+/// 添加或修改分类，修改对之后开始的下载生效
+#[handler]
+pub async fn set_category(depot: &mut Depot, req: &mut Request) -> Result<ApiResponse<()>, Error> {
+    let data: ReqData = req.parse_json().await?;
+    let name = data.name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("Category name is empty").into());
+    }
+    ConfigLock::from_depot(depot)?
+        .write()
+        .await
+        .categories
+        .insert(name.to_owned(), data.category);
+    Ok(ApiResponse::ok(()))
+}
@@ -0,0 +1,56 @@
+use super::*;
+use crate::password;
+use salvo::prelude::*;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct ReqData {
+    current_password: String,
+    new_password: String,
+}
+
+/// 修改当前用户的密码，需要提供当前密码，该用户的其他会话随之失效
+#[handler]
+pub async fn change_password(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<()>, Error> {
+    let json: ReqData = req.parse_json().await?;
+    if json.new_password.is_empty() {
+        return Err(anyhow!("New password must not be empty").into());
+    }
+    let name = CurrentUser::from_depot(depot)?.name.clone();
+    let config = ConfigLock::from_depot(depot)?;
+    let stored = config
+        .read()
+        .await
+        .account(&name)
+        .context("Account")?
+        .0
+        .to_owned();
+    // 校验和哈希都在持有配置锁之前完成
+    if !password::spawn_verify(stored.clone(), json.current_password).await? {
+        return Ok(ApiResponse::new(
+            Code::AuthenticationError,
+            (),
+            "Current password is incorrect",
+        ));
+    }
+    let hash = password::spawn_hash(json.new_password).await?;
+    let mut config = config.write().await;
+    // 校验期间密码被其他请求修改时，当前密码已经失效
+    if config.account(&name).context("Account")?.0 != stored {
+        return Ok(ApiResponse::new(
+            Code::AuthenticationError,
+            (),
+            "Current password is incorrect",
+        ));
+    }
+    config.set_password(&name, hash);
+    StateLock::from_depot(depot)?
+        .write()
+        .await
+        .sessions
+        .remove_others(request_token(req));
+    Ok(ApiResponse::ok(()))
+}
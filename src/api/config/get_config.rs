@@ -1,9 +1,17 @@
 use crate::api::*;
 use salvo::prelude::*;
 
+/// 获取当前配置，不返回密码，非管理员也不返回订阅源密钥等其他密钥
 #[handler]
 pub async fn get_config(depot: &mut Depot) -> Result<ApiResponse<Config>, Error> {
-    Ok(ApiResponse::ok(
-        ConfigLock::from_depot(&depot)?.read().await.clone(),
-    ))
+    let mut config = ConfigLock::from_depot(depot)?.read().await.clone();
+    config.password = String::new();
+    for user in config.users.values_mut() {
+        user.password = String::new();
+    }
+    if CurrentUser::from_depot(depot)?.role != Role::Admin {
+        config.feed_key = None;
+        config.token = None;
+    }
+    Ok(ApiResponse::ok(config))
 }
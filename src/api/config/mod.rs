@@ -1,2 +1,2 @@
-mod get_config;
-mod set_config;
+pub mod get_config;
+pub mod set_config;
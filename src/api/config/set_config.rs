@@ -1,9 +1,23 @@
 use crate::api::*;
+use crate::config;
 use salvo::prelude::*;
 
+#[derive(Serialize)]
+struct Resp {
+    /// 需要重启才能生效的字段
+    restart_required: Vec<&'static str>,
+}
+
+/// 校验并应用新配置，密码只能通过 `change_password` 修改，用户只能通过用户接口修改
 #[handler]
-pub async fn get_config(req: &mut Request, depot: &mut Depot) -> Result<ApiResponse<()>, Error> {
-    let config = req.parse_json().await?;
-    *ConfigLock::from_depot(&depot)?.write().await = config;
-    Ok(ApiResponse::ok(()))
+pub async fn set_config(req: &mut Request, depot: &mut Depot) -> Result<ApiResponse<Resp>, Error> {
+    let mut new: Config = req.parse_json().await?;
+    let config = ConfigLock::from_depot(depot)?;
+    {
+        let config = config.read().await;
+        new.password = config.password.clone();
+        new.users = config.users.clone();
+    }
+    let restart_required = config::apply(config, StateLock::from_depot(depot)?, new).await?;
+    Ok(ApiResponse::ok(Resp { restart_required }))
 }
@@ -1,6 +1,7 @@
 //use crate::download::Command;
 
 use crate::api::*;
+use crate::downloader::rqbit::DownloadTask;
 use crate::utils::FromDepot;
 use base64::prelude::*;
 use salvo::prelude::*;
@@ -17,6 +18,7 @@ struct RespData {
     task_id: usize,
 }
 
+/// 添加种子下载任务，记录添加任务的用户
 #[handler]
 pub async fn add_torrent_task(
     depot: &mut Depot,
@@ -30,7 +32,13 @@ pub async fn add_torrent_task(
         .await?;
     let handler = rx.await?;
     handler.wait_until_initialized().await?;
-    Ok(ApiResponse::ok(RespData {
-        task_id: handler.id(),
-    }))
+    let task_id = handler.id();
+    let owner = Some(CurrentUser::from_depot(depot)?.name.clone());
+    StateLock::from_depot(depot)?
+        .write()
+        .await
+        .download_tasks
+        .insert(task_id, DownloadTask { id: task_id, owner });
+    save_download_tasks(depot).await?;
+    Ok(ApiResponse::ok(RespData { task_id }))
 }
@@ -1,5 +1,18 @@
 use crate::api::*;
+use crate::downloader::rqbit::DownloadTask;
 use salvo::prelude::*;
 
+/// 获取当前用户可以修改的下载任务，管理员可以看到所有任务
 #[handler]
-pub async fn status(req: &mut Request, depot: &mut Depot) {}
+pub async fn status(depot: &mut Depot) -> Result<ApiResponse<Vec<DownloadTask>>, Error> {
+    let mut tasks: Vec<DownloadTask> = StateLock::from_depot(depot)?
+        .read()
+        .await
+        .download_tasks
+        .values()
+        .filter(|task| check_task_owner(depot, task).is_ok())
+        .cloned()
+        .collect();
+    tasks.sort_by_key(|task| task.id);
+    Ok(ApiResponse::ok(tasks))
+}
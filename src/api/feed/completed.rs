@@ -0,0 +1,178 @@
+use std::{cmp::Reverse, collections::BTreeMap, time::SystemTime};
+
+use super::{base_url, check_feed_key};
+use crate::{
+    api::*,
+    episode::{parse_title, EpisodeInfo},
+    opml::NEKODL_NAMESPACE,
+    rss::{RssItem, RssItemStatus},
+};
+use ::rss::{
+    extension::{Extension, ExtensionMap},
+    CategoryBuilder, ChannelBuilder, EnclosureBuilder, GuidBuilder, Item, ItemBuilder,
+};
+use quick_xml::escape::escape;
+use salvo::prelude::*;
+use time::{format_description::well_known::Rfc2822, OffsetDateTime};
+
+fn format_time(time: SystemTime) -> Option<String> {
+    OffsetDateTime::from(time).format(&Rfc2822).ok()
+}
+
+fn mime_type(filename: &str) -> &'static str {
+    match filename
+        .rsplit('.')
+        .next()
+        .map(|s| s.to_lowercase())
+        .as_deref()
+    {
+        Some("mkv") => "video/x-matroska",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("flac") => "audio/flac",
+        Some("mp3") => "audio/mpeg",
+        Some("ass") | Some("srt") => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+/// 将剧集信息写入 `nekodl:` 命名空间下的扩展元素
+fn episode_extensions(episode: &EpisodeInfo) -> ExtensionMap {
+    let fields = [
+        ("group", episode.group.clone()),
+        ("title", episode.title.clone()),
+        ("season", episode.season.map(|v| v.to_string())),
+        ("episode", episode.episode.map(|v| v.to_string())),
+        ("episodeEnd", episode.episode_end.map(|v| v.to_string())),
+        ("resolution", episode.resolution.clone()),
+    ];
+    let mut extensions = BTreeMap::new();
+    for (name, value) in fields {
+        if let Some(value) = value {
+            extensions.insert(
+                name.to_owned(),
+                vec![Extension {
+                    name: format!("nekodl:{}", name),
+                    value: Some(value),
+                    ..Default::default()
+                }],
+            );
+        }
+    }
+    let mut map = ExtensionMap::new();
+    map.insert("nekodl".to_owned(), extensions);
+    map
+}
+
+fn build_item(base: &str, auth: &str, rss_id: usize, rss_title: &str, item: &RssItem) -> Item {
+    let file_url = |index: usize| {
+        format!(
+            "{}/api/feed/file?{}&rss_id={}&item_id={}&file={}",
+            base, auth, rss_id, item.id, index
+        )
+    };
+    let files = item
+        .torrent
+        .as_ref()
+        .map(|t| t.files.as_slice())
+        .unwrap_or_default();
+    let mut description = String::from("<ul>");
+    for (index, file) in files.iter().enumerate() {
+        description.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>",
+            escape(&file_url(index)),
+            escape(&file.filename)
+        ));
+    }
+    description.push_str("</ul>");
+
+    let mut builder = ItemBuilder::default();
+    builder
+        .title(Some(item.title.clone()))
+        .guid(Some(
+            GuidBuilder::default()
+                .value(format!("nekodl-{}-{}", rss_id, item.id))
+                .permalink(false)
+                .build(),
+        ))
+        .pub_date(item.downloaded_time.and_then(format_time))
+        .categories(vec![CategoryBuilder::default()
+            .name(rss_title.to_owned())
+            .build()])
+        .description(Some(description))
+        .extensions(episode_extensions(&parse_title(&item.title)));
+    // 以最大的文件作为条目的链接，通常是视频文件
+    if let Some((index, file)) = files.iter().enumerate().max_by_key(|(_, f)| f.length) {
+        builder.link(Some(file_url(index))).enclosure(Some(
+            EnclosureBuilder::default()
+                .url(file_url(index))
+                .length(file.length.to_string())
+                .mime_type(mime_type(&file.filename).to_owned())
+                .build(),
+        ));
+    }
+    builder.build()
+}
+
+/// 最近完成下载的条目的 RSS 订阅源
+///
+/// # 参数
+/// * `key` - 查询参数，配置中的 `feed_key`，使用 API 密钥时不需要
+/// * `apikey` - 查询参数，可选，带有 `read` 范围的 API 密钥，文件链接也会使用该密钥
+/// * `rss_id` - 查询参数，可选，只返回该订阅的条目
+/// * `tag` - 查询参数，可选，只返回带有该标签或属于该分类的订阅的条目
+/// * `limit` - 查询参数，可选，返回的条目数量，默认为 50
+#[handler]
+pub async fn completed(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+) -> Result<(), Error> {
+    let auth = check_feed_key(req, depot).await?;
+    let rss_id: Option<usize> = req.query("rss_id");
+    let tag: Option<String> = req.query("tag");
+    let limit: usize = req.query("limit").unwrap_or(50);
+    let base = base_url(req, &*ConfigLock::from_depot(depot)?.read().await);
+
+    let mut entries = Vec::new();
+    for rss in DataBaseLock::from_depot(depot)?
+        .read()
+        .await
+        .rss_list
+        .values()
+    {
+        let rss = rss.read().await;
+        if rss_id.is_some_and(|id| id != rss.id)
+            || tag.as_ref().is_some_and(|tag| !rss.has_tag(tag))
+        {
+            continue;
+        }
+        for item in rss.items.iter() {
+            let item = item.read().await;
+            if item.status == RssItemStatus::Downloaded {
+                entries.push((rss.id, rss.title.clone(), item.clone()));
+            }
+        }
+    }
+    entries.sort_by_key(|(_, _, item)| Reverse(item.downloaded_time));
+    entries.truncate(limit);
+
+    let channel = ChannelBuilder::default()
+        .title("nekodl completed downloads".to_owned())
+        .link(base.clone())
+        .description("Recently completed downloads".to_owned())
+        .namespaces(BTreeMap::from([(
+            "nekodl".to_owned(),
+            NEKODL_NAMESPACE.to_owned(),
+        )]))
+        .items(
+            entries
+                .iter()
+                .map(|(rss_id, rss_title, item)| build_item(&base, &auth, *rss_id, rss_title, item))
+                .collect::<Vec<_>>(),
+        )
+        .build();
+    res.add_header("content-type", "application/rss+xml; charset=utf-8", true)?;
+    res.write_body(channel.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use std::path::{Component, Path};
+
+use super::check_feed_key;
+use crate::api::*;
+use salvo::fs::NamedFile;
+use salvo::prelude::*;
+
+/// 提供已下载文件的访问，支持 Range 请求以便播放器直接串流
+///
+/// # 参数
+/// * `key` - 查询参数，配置中的 `feed_key`，使用 API 密钥时不需要
+/// * `apikey` - 查询参数，可选，带有 `read` 范围的 API 密钥
+/// * `rss_id` - 查询参数，订阅 ID
+/// * `item_id` - 查询参数，条目 ID
+/// * `file` - 查询参数，文件在种子中的序号
+#[handler]
+pub async fn file(req: &mut Request, depot: &mut Depot, res: &mut Response) -> Result<(), Error> {
+    check_feed_key(req, depot).await?;
+    let rss_id: usize = req.query("rss_id").context("rss_id")?;
+    let item_id: usize = req.query("item_id").context("item_id")?;
+    let index: usize = req.query("file").context("file")?;
+    let path = {
+        let config = ConfigLock::from_depot(depot)?.read().await;
+        let db = DataBaseLock::from_depot(depot)?.read().await;
+        let rss = db
+            .rss_list
+            .get(&rss_id)
+            .context("Rss not found")?
+            .read()
+            .await;
+        let filename = rss
+            .find_item(item_id)
+            .await
+            .context("Item not found")?
+            .read()
+            .await
+            .torrent
+            .as_ref()
+            .and_then(|t| t.files.get(index))
+            .map(|f| f.filename.clone())
+            .context("File not found")?;
+        // 防止文件名跳出下载目录
+        if !Path::new(&filename)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(anyhow!("Invalid file name").into());
+        }
+        rss.output_dir(&config).join(filename)
+    };
+    NamedFile::builder(path).send(req.headers(), res).await;
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use crate::{api::*, utils::constant_time_eq};
+use salvo::prelude::*;
+use url::form_urlencoded;
+
+pub mod completed;
+pub mod file;
+
+/// 生成链接中经过编码的认证参数
+fn auth_query(name: &str, value: &str) -> String {
+    form_urlencoded::Serializer::new(String::new())
+        .append_pair(name, value)
+        .finish()
+}
+
+/// 校验订阅源密钥，返回生成链接时使用的认证参数。
+/// 阅读器和媒体服务器无法携带 Token，因此通过 `key` 查询参数认证，
+/// 也可以使用带有 `read` 范围的 API 密钥
+async fn check_feed_key(req: &Request, depot: &Depot) -> Result<String, Error> {
+    if let Some(api_key) = request_api_key(req) {
+        authenticate_api_key(depot, &api_key, Scope::Read).await?;
+        return Ok(auth_query("apikey", &api_key));
+    }
+    let key: String = req.query("key").context("key")?;
+    match &ConfigLock::from_depot(depot)?.read().await.feed_key {
+        Some(feed_key) if constant_time_eq(feed_key.as_bytes(), key.as_bytes()) => {
+            Ok(auth_query("key", &key))
+        }
+        _ => Err(anyhow!("Invalid feed key").into()),
+    }
+}
+
+/// 生成链接所用的地址，优先使用配置中的 `public_url`，
+/// 否则使用请求的协议和 Host，启用 HTTPS 时为 `https`
+fn base_url(req: &Request, config: &Config) -> String {
+    if let Some(url) = &config.public_url {
+        return url.trim_end_matches('/').to_owned();
+    }
+    let host = req
+        .headers()
+        .get("host")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("localhost:8001");
+    format!("{}://{}", req.scheme(), host)
+}
@@ -1,8 +1,9 @@
 use super::*;
-use crate::utils::rand_str;
+use crate::password;
 use crate::utils::FromDepot;
 use salvo::prelude::*;
 use serde::{Deserialize, Serialize};
+use tracing::info;
 
 #[derive(Serialize)]
 struct Resp {
@@ -15,27 +16,51 @@ struct ReqData {
     password: String,
 }
 
+/// 登录并创建新的会话，不影响其他会话。
+/// 密码在服务端校验，旧版本的 SHA-256 哈希在登录成功后升级为 Argon2id
 #[handler]
 pub async fn login(
     depot: &mut Depot,
     req: &mut Request,
 ) -> Result<ApiResponse<Option<Resp>>, Error> {
-    let config = ConfigLock::from_depot(depot)?.read().await;
     let json: ReqData = req.parse_json().await?;
-    let token = rand_str(16);
-    {
-        let mut state = StateLock::from_depot(depot)?.write().await;
-        state.token = Some(token.clone());
+    let config = ConfigLock::from_depot(depot)?;
+    let stored = {
+        let config = config.read().await;
+        config
+            .account(&json.username)
+            .map(|(password, _)| password.to_owned())
+    };
+    let verified = match &stored {
+        Some(stored) => password::spawn_verify(stored.clone(), json.password.clone()).await?,
+        // 用户名错误时也计算一次哈希，避免通过响应时间判断用户名
+        None => {
+            let _ = password::spawn_hash(json.password.clone()).await;
+            false
+        }
+    };
+    if !verified {
+        return Ok(ApiResponse::new(
+            Code::AuthenticationError,
+            None,
+            "Username or password is incorrect",
+        ));
     }
-    Ok(
-        if config.password == json.password && config.username == json.username {
-            ApiResponse::new(Code::Success, Some(Resp { token }), "")
-        } else {
-            ApiResponse::new(
-                Code::AuthenticationError,
-                None,
-                "Username or password is incorrect",
-            )
-        },
-    )
+    if stored.as_deref().is_some_and(password::is_legacy) {
+        let hash = password::spawn_hash(json.password.clone()).await?;
+        config.write().await.set_password(&json.username, hash);
+        info!("Upgraded password hash of {} to Argon2id", json.username);
+    }
+    let user_agent = req
+        .headers()
+        .get("User-Agent")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned);
+    let address = Some(req.remote_addr().to_string());
+    let token = StateLock::from_depot(depot)?.write().await.sessions.create(
+        &json.username,
+        user_agent,
+        address,
+    );
+    Ok(ApiResponse::new(Code::Success, Some(Resp { token }), ""))
 }
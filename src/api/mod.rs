@@ -1,20 +1,36 @@
 use anyhow::anyhow;
-use salvo::{async_trait, Depot, FlowCtrl, Handler, Request, Response, Router, Writer};
+use salvo::{
+    async_trait, http::StatusCode, Depot, FlowCtrl, Handler, Request, Response, Router, Writer,
+};
 use serde::Serialize;
 use std::{fmt::Display, sync::Arc, vec};
 use tokio::sync::RwLock;
 use tracing::error;
 
 use crate::{
+    api_key::Scope,
+    downloader::rqbit::DownloadTask,
+    rss::Rss,
+    series::Series,
     state::{Config, DataBase, State},
+    store::Store,
+    user::{CurrentUser, Role},
     utils::FromDepot,
 };
 
+mod api_key;
 mod auth;
+mod calendar;
+mod category;
+mod change_password;
 mod config;
 mod download;
+mod feed;
 mod login;
 mod rss;
+mod series;
+mod session;
+mod user;
 
 type DataBaseLock = Arc<RwLock<DataBase>>;
 type StateLock = Arc<RwLock<State>>;
@@ -23,14 +39,80 @@ type ConfigLock = Arc<RwLock<Config>>;
 pub fn routes() -> Vec<Router> {
     vec![
         Router::with_path("login").post(login::login),
-        Router::new().hoop(ApiHandler).append(&mut vec![
-            Router::with_path("add_torrent_task")
-                .post(download::add_torrent_task::add_torrent_task),
-            Router::with_path("auth").get(auth::auth),
-            Router::with_path("get_rss_list").get(rss::get_rss_list::get_rss_list),
-            Router::with_path("add_rss_sub").post(rss::add_rss_sub::add_rss_sub),
-            Router::with_path("get_rss_info").post(rss::get_rss_info::get_rss_info),
-        ]),
+        // 订阅源通过 key 参数或 API 密钥认证，不经过 ApiHandler
+        Router::with_path("feed/completed").get(feed::completed::completed),
+        Router::with_path("feed/file").get(feed::file::file),
+        // 账户相关的操作只能通过登录访问
+        Router::new()
+            .hoop(ApiHandler(Role::Viewer, None))
+            .append(&mut vec![
+                Router::with_path("change_password").post(change_password::change_password),
+                Router::with_path("logout").post(session::logout::logout),
+                Router::with_path("logout_all").post(session::logout_all::logout_all),
+                Router::with_path("get_session_list")
+                    .get(session::get_session_list::get_session_list),
+                Router::with_path("revoke_session").post(session::revoke_session::revoke_session),
+                Router::with_path("get_api_key_list")
+                    .get(api_key::get_api_key_list::get_api_key_list),
+                Router::with_path("create_api_key").post(api_key::create_api_key::create_api_key),
+                Router::with_path("revoke_api_key").post(api_key::revoke_api_key::revoke_api_key),
+            ]),
+        // 所有登录的用户都可以访问
+        Router::new()
+            .hoop(ApiHandler(Role::Viewer, Some(Scope::Read)))
+            .append(&mut vec![
+                Router::with_path("auth").get(auth::auth),
+                Router::with_path("get_rss_list").get(rss::get_rss_list::get_rss_list),
+                Router::with_path("get_rss_info").post(rss::get_rss_info::get_rss_info),
+                Router::with_path("get_item_torrent").post(rss::get_item_torrent::get_item_torrent),
+                Router::with_path("download_item_torrent")
+                    .get(rss::download_item_torrent::download_item_torrent),
+                Router::with_path("search_items").post(rss::search_items::search_items),
+                Router::with_path("get_category_list")
+                    .get(category::get_category_list::get_category_list),
+                Router::with_path("get_series_list").get(series::get_series_list::get_series_list),
+                Router::with_path("get_series_info").post(series::get_series_info::get_series_info),
+                Router::with_path("get_calendar").get(calendar::get_calendar::get_calendar),
+                Router::with_path("export_opml").get(rss::export_opml::export_opml),
+            ]),
+        // 修改订阅，订阅只能由所有者或管理员修改
+        Router::new()
+            .hoop(ApiHandler(Role::Member, Some(Scope::Feeds)))
+            .append(&mut vec![
+                Router::with_path("add_rss_sub").post(rss::add_rss_sub::add_rss_sub),
+                Router::with_path("set_item_status").post(rss::set_item_status::set_item_status),
+                Router::with_path("mark_rss_read").post(rss::mark_rss_read::mark_rss_read),
+                Router::with_path("bulk_set_item_status")
+                    .post(rss::bulk_set_item_status::bulk_set_item_status),
+                Router::with_path("set_rss_tags").post(rss::set_rss_tags::set_rss_tags),
+                Router::with_path("set_rss_polling").post(rss::set_rss_polling::set_rss_polling),
+                Router::with_path("set_rss_retention")
+                    .post(rss::set_rss_retention::set_rss_retention),
+                Router::with_path("import_opml").post(rss::import_opml::import_opml),
+                Router::with_path("set_series").post(series::set_series::set_series),
+                Router::with_path("remove_series").post(series::remove_series::remove_series),
+            ]),
+        // 下载任务
+        Router::new()
+            .hoop(ApiHandler(Role::Member, Some(Scope::Downloads)))
+            .append(&mut vec![
+                Router::with_path("add_torrent_task")
+                    .post(download::add_torrent_task::add_torrent_task),
+                Router::with_path("get_download_tasks").get(download::status::status),
+                Router::with_path("fetch_episode").post(series::fetch_episode::fetch_episode),
+            ]),
+        Router::new()
+            .hoop(ApiHandler(Role::Admin, None))
+            .append(&mut vec![
+                Router::with_path("get_config").get(config::get_config::get_config),
+                Router::with_path("set_config").post(config::set_config::set_config),
+                Router::with_path("set_category").post(category::set_category::set_category),
+                Router::with_path("remove_category")
+                    .post(category::remove_category::remove_category),
+                Router::with_path("get_user_list").get(user::get_user_list::get_user_list),
+                Router::with_path("set_user").post(user::set_user::set_user),
+                Router::with_path("remove_user").post(user::remove_user::remove_user),
+            ]),
     ]
 }
 
@@ -61,6 +143,7 @@ impl<T: Serialize> ApiResponse<T> {
 #[derive(Debug)]
 pub enum Code {
     Success,
+    BadRequest,
     AuthenticationError,
     ServerError,
 }
@@ -72,6 +155,7 @@ impl Serialize for Code {
     {
         serializer.serialize_u64(match self {
             Self::Success => 200,
+            Self::BadRequest => 400,
             Self::AuthenticationError => 501,
             Self::ServerError => 502,
         })
@@ -80,6 +164,20 @@ impl Serialize for Code {
 
 pub struct Error {
     pub inner: anyhow::Error,
+    pub code: Code,
+}
+
+impl Error {
+    /// 请求参数错误，响应 400
+    pub fn bad_request<C>(msg: C) -> Self
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        Self {
+            inner: anyhow!("{}", msg),
+            code: Code::BadRequest,
+        }
+    }
 }
 
 impl<T> From<T> for Error
@@ -89,6 +187,7 @@ where
     fn from(value: T) -> Self {
         Self {
             inner: value.into(),
+            code: Code::ServerError,
         }
     }
 }
@@ -116,9 +215,12 @@ impl<T> Context<T> for Option<T> {
 #[async_trait]
 impl Writer for Error {
     async fn write(self, _req: &mut Request, _depot: &mut Depot, res: &mut Response) {
+        if let Code::BadRequest = self.code {
+            res.status_code(StatusCode::BAD_REQUEST);
+        }
         res.body(
             serde_json::to_string(&ApiResponse::new(
-                Code::ServerError,
+                self.code,
                 Option::<()>::None,
                 self.inner.to_string().as_str(),
             ))
@@ -131,7 +233,94 @@ impl Writer for Error {
     }
 }
 
-struct ApiHandler;
+/// 请求头中的登录令牌
+fn request_token(req: &Request) -> &str {
+    req.headers()
+        .get("Token")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+}
+
+/// 请求中的 API 密钥，无法设置请求头的客户端可以使用 `apikey` 查询参数
+fn request_api_key(req: &Request) -> Option<String> {
+    req.headers()
+        .get("X-Api-Key")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_owned())
+        .or_else(|| req.query("apikey"))
+}
+
+/// 校验 API 密钥的范围，返回密钥所属的用户，角色为用户当前的角色
+async fn authenticate_api_key(
+    depot: &Depot,
+    key: &str,
+    scope: Scope,
+) -> anyhow::Result<CurrentUser> {
+    let config = ConfigLock::from_depot(depot)?.read().await;
+    let name = {
+        let mut state = StateLock::from_depot(depot)?.write().await;
+        let info = state
+            .api_keys
+            .validate(key)
+            .ok_or_else(|| anyhow!("Invalid API key"))?;
+        if !info.allows(scope) {
+            return Err(anyhow!("API key is not allowed to access this endpoint"));
+        }
+        info.username.clone()
+    };
+    let (_, role) = config
+        .account(&name)
+        .ok_or_else(|| anyhow!("Invalid API key"))?;
+    Ok(CurrentUser { name, role })
+}
+
+/// 认证请求并检查角色，通过后将 `CurrentUser` 写入 `Depot`。
+/// 第二个参数为 API 密钥访问所需的范围，为 `None` 时只能通过登录访问
+struct ApiHandler(Role, Option<Scope>);
+
+impl ApiHandler {
+    async fn authenticate(&self, req: &Request, depot: &Depot) -> anyhow::Result<CurrentUser> {
+        let user = match request_api_key(req) {
+            Some(key) => {
+                let scope = self
+                    .1
+                    .ok_or_else(|| anyhow!("API keys cannot access this endpoint"))?;
+                authenticate_api_key(depot, &key, scope).await?
+            }
+            None => self.authenticate_session(req, depot).await?,
+        };
+        if user.role < self.0 {
+            return Err(anyhow!("Permission denied"));
+        }
+        Ok(user)
+    }
+
+    async fn authenticate_session(
+        &self,
+        req: &Request,
+        depot: &Depot,
+    ) -> anyhow::Result<CurrentUser> {
+        let config = ConfigLock::from_depot(depot)?.read().await;
+        // 调试时不需要登录，视为内置账户
+        if cfg!(debug_assertions) {
+            return Ok(CurrentUser {
+                name: config.username.clone(),
+                role: Role::Admin,
+            });
+        }
+        let name = StateLock::from_depot(depot)?
+            .write()
+            .await
+            .sessions
+            .validate(request_token(req), &config.session_options)
+            .map(|session| session.username.clone())
+            .ok_or_else(|| anyhow!("Invalid or expired token"))?;
+        let (_, role) = config
+            .account(&name)
+            .ok_or_else(|| anyhow!("Invalid or expired token"))?;
+        Ok(CurrentUser { name, role })
+    }
+}
 
 #[async_trait]
 impl Handler for ApiHandler {
@@ -142,46 +331,71 @@ impl Handler for ApiHandler {
         res: &mut Response,
         ctrl: &mut FlowCtrl,
     ) {
-        #[cfg(debug_assertions)]
-        {
-            ctrl.call_next(req, depot, res).await;
-            return;
-        }
-        #[cfg(not(debug_assertions))]
-        {
-            let state = match StateLock::from_depot(&depot) {
-                Ok(state) => state.clone(),
-                Err(err) => {
-                    Error::from(err).write(req, depot, res).await;
-                    ctrl.skip_rest();
-                    return;
-                }
-            };
-
-            let token = req
-                .headers()
-                .get("Token")
-                .map(|v| v.to_str().unwrap_or(""))
-                .unwrap_or("");
-            if token
-                != match &state.read().await.token {
-                    Some(token) => token,
-                    None => {
-                        Error::from(anyhow!("Missing token"))
-                            .write(req, depot, res)
-                            .await;
-                        ctrl.skip_rest();
-                        return;
-                    }
-                }
-            {
-                Error::from(anyhow!("Invalid token"))
-                    .write(req, depot, res)
-                    .await;
-                ctrl.skip_rest();
-            } else {
+        match self.authenticate(req, depot).await {
+            Ok(user) => {
+                depot.inject(user);
                 ctrl.call_next(req, depot, res).await;
             }
+            Err(err) => {
+                Error::from(err).write(req, depot, res).await;
+                ctrl.skip_rest();
+            }
         }
     }
 }
+
+/// 检查当前用户是否可以修改订阅
+fn check_owner(depot: &Depot, rss: &Rss) -> Result<(), Error> {
+    if CurrentUser::from_depot(depot)?.can_modify(rss.owner.as_deref()) {
+        Ok(())
+    } else {
+        Err(anyhow!("Rss {} is owned by another user", rss.id).into())
+    }
+}
+
+/// 检查当前用户是否可以修改剧集
+fn check_series_owner(depot: &Depot, series: &Series) -> Result<(), Error> {
+    if CurrentUser::from_depot(depot)?.can_modify(series.owner.as_deref()) {
+        Ok(())
+    } else {
+        Err(anyhow!("Series {} is owned by another user", series.id).into())
+    }
+}
+
+/// 检查当前用户是否可以查看和修改下载任务
+fn check_task_owner(depot: &Depot, task: &DownloadTask) -> Result<(), Error> {
+    if CurrentUser::from_depot(depot)?.can_modify(task.owner.as_deref()) {
+        Ok(())
+    } else {
+        Err(anyhow!("Download task {} is owned by another user", task.id).into())
+    }
+}
+
+/// 立即保存下载任务，任务的所有者在重启后保持不变
+async fn save_download_tasks(depot: &Depot) -> Result<(), Error> {
+    let data = serde_json::to_string(&StateLock::from_depot(depot)?.read().await.download_tasks)?;
+    Store::from_depot(depot)?.save_download_tasks(data).await?;
+    Ok(())
+}
+
+/// 立即保存 API 密钥，创建和吊销的密钥在重启后保持不变
+async fn save_api_keys(depot: &Depot) -> Result<(), Error> {
+    let data = serde_json::to_string(&StateLock::from_depot(depot)?.read().await.api_keys)?;
+    Store::from_depot(depot)?.save_api_keys(data).await?;
+    Ok(())
+}
+
+/// 立即保存会话，注销的会话在重启后不会恢复，不保存会话时不做任何事
+async fn save_sessions(depot: &Depot) -> Result<(), Error> {
+    if !ConfigLock::from_depot(depot)?
+        .read()
+        .await
+        .session_options
+        .persist
+    {
+        return Ok(());
+    }
+    let data = serde_json::to_string(&StateLock::from_depot(depot)?.read().await.sessions)?;
+    Store::from_depot(depot)?.save_sessions(Some(data)).await?;
+    Ok(())
+}
@@ -1,8 +1,6 @@
-use std::time::{Duration, SystemTime};
-
 use crate::{
     event::Event,
-    rss::{fetch_channel, Rss, RssStatus},
+    rss::{fetch_channel, Rss},
 };
 
 use crate::api::*;
@@ -42,17 +40,11 @@ pub async fn add_rss_sub(depot: &mut Depot, req: &mut Request) -> Result<ApiResp
         db.rss_id_index
     };
 
-    // 创建RSS对象
+    // 创建RSS对象，当前用户为所有者
     let rss = Rss {
-        id: id,
-        title: title,
-        url: data.url,
-        items: Vec::new(),
-        description: description,
-        update_time: SystemTime::now(),
-        update_interval: Duration::from_secs(3600),
-        status: RssStatus::Created,
         auto_download: data.auto_download,
+        owner: Some(CurrentUser::from_depot(depot)?.name.clone()),
+        ..Rss::new(id, data.url, title, description)
     };
 
     // 发送添加RSS的事件
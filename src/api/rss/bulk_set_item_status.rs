@@ -0,0 +1,69 @@
+use crate::api::*;
+use crate::event::Event;
+use crate::rss::{ItemFilter, RssItemStatus};
+use salvo::prelude::*;
+use serde::Deserialize;
+use tokio::sync::mpsc::Sender;
+
+#[derive(Deserialize)]
+struct ReqData {
+    filter: ItemFilter,
+    status: RssItemStatus,
+}
+
+#[derive(Serialize)]
+struct Resp {
+    /// 状态被修改的条目数量
+    changed: usize,
+    /// 因处于下载状态而被跳过的条目数量
+    skipped: usize,
+}
+
+/// 对筛选出的条目批量设置状态，只修改当前用户可以修改的订阅
+#[handler]
+pub async fn bulk_set_item_status(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<Resp>, Error> {
+    let data: ReqData = req.parse_json().await?;
+    if !data.status.is_user_settable() {
+        return Err(anyhow!("Status {:?} can not be set manually", data.status).into());
+    }
+    let mut resp = Resp {
+        changed: 0,
+        skipped: 0,
+    };
+    let user = CurrentUser::from_depot(depot)?.clone();
+    // 有条目被修改的订阅，只保存这些订阅
+    let mut changed_rss = Vec::new();
+    {
+        let db = DataBaseLock::from_depot(depot)?.read().await;
+        for rss in db.rss_list.values() {
+            let rss = rss.read().await;
+            // 跳过其他用户的订阅
+            if !data.filter.matches_rss(&rss) || !user.can_modify(rss.owner.as_deref()) {
+                continue;
+            }
+            let changed = resp.changed;
+            for item in rss.items.iter() {
+                let mut item = item.write().await;
+                if !data.filter.matches_item(&item) {
+                    continue;
+                }
+                match item.set_user_status(data.status.clone()) {
+                    Ok(true) => resp.changed += 1,
+                    Ok(false) => {}
+                    Err(_) => resp.skipped += 1,
+                }
+            }
+            if resp.changed > changed {
+                changed_rss.push(rss.id);
+            }
+        }
+    }
+    let sender = Sender::<Event>::from_depot(depot)?;
+    for rss_id in changed_rss {
+        sender.send(Event::SaveRss(rss_id)).await?;
+    }
+    Ok(ApiResponse::ok(resp))
+}
@@ -0,0 +1,57 @@
+use crate::{api::*, event::Event, torrent_cache::TorrentCache};
+use salvo::prelude::*;
+use tokio::sync::mpsc::Sender;
+
+/// 下载条目的种子文件，缓存中没有时从条目链接下载并写入缓存
+///
+/// # 参数
+/// * `rss_id` - 查询参数，订阅 ID
+/// * `item_id` - 查询参数，条目 ID
+#[handler]
+pub async fn download_item_torrent(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+) -> Result<(), Error> {
+    let rss_id: usize = req.query("rss_id").context("rss_id")?;
+    let item_id: usize = req.query("item_id").context("item_id")?;
+    let cache = TorrentCache::new(&ConfigLock::from_depot(depot)?.read().await.session_path);
+    let item = {
+        let db = DataBaseLock::from_depot(depot)?.read().await;
+        let rss = db
+            .rss_list
+            .get(&rss_id)
+            .context("Rss not found")?
+            .read()
+            .await;
+        rss.find_item(item_id).await.cloned()
+    }
+    .context("Item not found")?;
+    let (link, info_hash) = {
+        let item = item.read().await;
+        (item.link.clone(), item.info_hash.clone())
+    };
+    let (info_hash, data) = cache
+        .fetch(&link, info_hash.as_deref())
+        .await?
+        .context("Torrent file of magnet link")?;
+    let changed = {
+        let mut item = item.write().await;
+        let changed = item.info_hash.as_deref() != Some(info_hash.as_str());
+        item.info_hash = Some(info_hash.clone());
+        changed
+    };
+    if changed {
+        Sender::<Event>::from_depot(depot)?
+            .send(Event::SaveRss(rss_id))
+            .await?;
+    }
+    res.add_header("content-type", "application/x-bittorrent", true)?;
+    res.add_header(
+        "content-disposition",
+        format!("attachment; filename=\"{}.torrent\"", info_hash),
+        true,
+    )?;
+    res.write_body(data)?;
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use crate::api::*;
+use crate::opml::{export_opml as export, OpmlFeed};
+use salvo::prelude::*;
+
+/// 将所有订阅导出为 OPML 文件
+#[handler]
+pub async fn export_opml(depot: &mut Depot, res: &mut Response) -> Result<(), Error> {
+    let mut feeds = Vec::new();
+    for rss in DataBaseLock::from_depot(depot)?
+        .read()
+        .await
+        .rss_list
+        .values()
+    {
+        feeds.push(OpmlFeed::from_rss(&*rss.read().await));
+    }
+    // 按地址排序，保证导出结果稳定
+    feeds.sort_by(|a, b| a.url.cmp(&b.url));
+    res.add_header(
+        "content-disposition",
+        "attachment; filename=\"nekodl.opml\"",
+        true,
+    )?;
+    res.render(Text::Xml(export(&feeds)?));
+    Ok(())
+}
@@ -1,7 +1,12 @@
-use crate::{api::*, rss::ItemTorrent, torrent::fetch_torrent_for_item};
-use librqbit::AddTorrent;
+use std::time::Duration;
+
+use crate::{
+    api::*, event::Event, metadata::MetadataState, rss::ItemTorrent,
+    torrent::fetch_torrent_for_item, torrent_cache::TorrentCache,
+};
 use salvo::prelude::*;
 use serde::Deserialize;
+use tokio::sync::mpsc::Sender;
 
 #[derive(Deserialize)]
 struct ReqData {
@@ -9,43 +14,69 @@ struct ReqData {
     item_id: usize,
 }
 
+/// 获取条目的种子文件列表，尚未获取时立即获取，超时时间与元数据队列相同
 #[handler]
 pub async fn get_item_torrent(
     req: &mut Request,
     depot: &mut Depot,
 ) -> Result<ApiResponse<ItemTorrent>, Error> {
     let reqdata: ReqData = req.parse_json().await?;
-    let db = DataBaseLock::from_depot(&depot)?.read().await;
-    let rss = db
-        .rss_list
-        .get(&reqdata.rss_id)
-        .context("Rss not found")?
-        .read()
-        .await;
-    let item = rss.items.get(reqdata.item_id).context("Item not found")?;
-    if let Some(torrent) = item.read().await.torrent.clone() {
+    // 获取种子前释放数据库和订阅的锁
+    let (item, category) = {
+        let db = DataBaseLock::from_depot(&depot)?.read().await;
+        let rss = db
+            .rss_list
+            .get(&reqdata.rss_id)
+            .context("Rss not found")?
+            .read()
+            .await;
+        let item = rss
+            .find_item(reqdata.item_id)
+            .await
+            .context("Item not found")?
+            .weak();
+        (item, rss.category.clone())
+    };
+    let lock = item.upgrade().context("Item not found")?;
+    if let Some(torrent) = lock.read().await.torrent.clone() {
         return Ok(ApiResponse::ok(torrent));
     }
-    // TODO: 从数据库中读取种子文件
-    let add_torrent = AddTorrent::Url(item.read().await.link.clone().into());
-    let session = StateLock::from_depot(&depot)?
-        .read()
-        .await
-        .rqbit_session
-        .clone()
-        .context("Session not initialized")?;
-    Ok(ApiResponse::ok(
-        fetch_torrent_for_item(
-            add_torrent,
-            session,
-            ConfigLock::from_depot(&depot)?
-                .read()
-                .await
-                .torrent_options
-                .trackers
-                .clone(),
-            item.weak(),
+    drop(lock);
+    let (session, metadata_queue) = {
+        let state = StateLock::from_depot(&depot)?.read().await;
+        (
+            state
+                .rqbit_session
+                .clone()
+                .context("Session not initialized")?,
+            state.metadata_queue.clone(),
+        )
+    };
+    // 元数据队列正在获取的条目不再重复获取
+    if metadata_queue.is_in_flight((reqdata.rss_id, reqdata.item_id)) {
+        return Err(anyhow!("Metadata is being fetched, try again later").into());
+    }
+    let (trackers, cache, timeout) = {
+        let config = ConfigLock::from_depot(&depot)?.read().await;
+        (
+            config.trackers(category.as_deref()),
+            TorrentCache::new(&config.session_path),
+            config.metadata_options.timeout,
         )
-        .await?,
-    ))
+    };
+    // 种子文件优先从缓存中读取
+    let torrent = tokio::time::timeout(
+        Duration::from_secs(timeout),
+        fetch_torrent_for_item(&cache, session, trackers, item.clone()),
+    )
+    .await
+    .map_err(|_| anyhow!("Timed out after {}s", timeout))??;
+    if let Some(lock) = item.upgrade() {
+        lock.write().await.metadata = MetadataState::Fetched;
+    }
+    // 保存种子文件列表和作为缓存键的 infohash
+    Sender::<Event>::from_depot(depot)?
+        .send(Event::SaveRss(reqdata.rss_id))
+        .await?;
+    Ok(ApiResponse::ok(torrent))
 }
@@ -1,31 +1,47 @@
-use crate::rss::Rss;
+use std::cmp::Reverse;
+
+use crate::rss::{Rss, RssItem};
 
 use crate::api::*;
 use crate::state::CloneInner;
 use salvo::prelude::*;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize)]
 struct ReqData {
     id: usize,
 }
+
+#[derive(Serialize)]
+struct Resp {
+    #[serde(flatten)]
+    rss: Rss,
+    items: Vec<RssItem>,
+}
+
 //获取RSS信息
 #[handler]
-pub async fn get_rss_info(depot: &mut Depot, req: &mut Request) -> Result<ApiResponse<Rss>, Error> {
+pub async fn get_rss_info(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<Resp>, Error> {
     // 解析请求中的JSON数据
     let data: ReqData = req.parse_json().await?;
 
     // 从数据库中读取RSS列表，并查找与请求数据ID匹配的RSS项
     // 如果找到，则返回该项的克隆；如果没有找到，则返回错误信息
-    Ok(ApiResponse::ok(
-        DataBaseLock::from_depot(depot)?
-            .read()
-            .await
-            .rss_list
-            .get(&data.id)
-            .context("没有找到对应的RSS")?
-            .clone()
-            .clone_inner()
-            .await,
-    ))
+    let rss = DataBaseLock::from_depot(depot)?
+        .read()
+        .await
+        .rss_list
+        .get(&data.id)
+        .context("没有找到对应的RSS")?
+        .clone()
+        .clone_inner()
+        .await;
+
+    // 逐个读取条目生成快照，条目按发布时间从新到旧排序
+    let mut items = rss.items.iter().collect::<Vec<_>>().clone_inner().await;
+    items.sort_by_key(|item| Reverse(item.publish_time()));
+    Ok(ApiResponse::ok(Resp { rss, items }))
 }
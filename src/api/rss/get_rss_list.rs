@@ -2,19 +2,37 @@ use crate::api::*;
 use crate::rss::Rss;
 use salvo::prelude::*;
 
+#[derive(Serialize)]
+struct RssInfo {
+    #[serde(flatten)]
+    rss: Rss,
+    unread_count: usize,
+}
+
 #[derive(Serialize)]
 struct Resp {
-    rss_list: Vec<Rss>,
+    rss_list: Vec<RssInfo>,
 }
 
 /// 获取RSS列表的处理函数。
-/// 该函数从数据库中读取RSS列表，并将其转换为响应格式。
+/// 该函数从数据库中读取RSS列表，并将其转换为响应格式，同时附带每个订阅的未读条目数。
 /// # Arguments
 /// * `depot` - 一个可变的Depot引用，用于访问数据存储。
+/// * `tag` - 查询参数，可选，只返回带有该标签或属于该分类的订阅。
+/// * `mine` - 查询参数，可选，为 `true` 时只返回当前用户添加的订阅。
 /// # Returns
 /// * `Result<ApiResponse<Resp>, Error>` - 如果成功，则返回包含RSS列表的响应；如果失败，则返回错误。
 #[handler]
-pub async fn get_rss_list(depot: &mut Depot) -> Result<ApiResponse<Resp>, Error> {
+pub async fn get_rss_list(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<Resp>, Error> {
+    let tag: Option<String> = req.query("tag");
+    let owner = req
+        .query::<bool>("mine")
+        .unwrap_or(false)
+        .then(|| CurrentUser::from_depot(depot).map(|user| user.name.clone()))
+        .transpose()?;
     let mut res = Vec::new();
     // 从Depot中读取数据
     for i in DataBaseLock::from_depot(depot)?
@@ -23,7 +41,17 @@ pub async fn get_rss_list(depot: &mut Depot) -> Result<ApiResponse<Resp>, Error>
         .rss_list
         .values()
     {
-        res.push(i.read().await.info());
+        let rss = i.read().await;
+        if tag.as_ref().is_some_and(|tag| !rss.has_tag(tag)) {
+            continue;
+        }
+        if owner.is_some() && rss.owner != owner {
+            continue;
+        }
+        res.push(RssInfo {
+            rss: rss.info(),
+            unread_count: rss.unread_count().await,
+        });
     }
     // 创建一个新的ApiResponse对象，状态码为成功
     Ok(ApiResponse::new(
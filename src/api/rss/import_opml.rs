@@ -0,0 +1,129 @@
+use std::collections::HashSet;
+
+use crate::{
+    event::Event,
+    opml::parse_opml,
+    rss::{check_category, check_update_interval, fetch_channel, Rss},
+};
+
+use crate::api::*;
+use salvo::prelude::*;
+use serde::Deserialize;
+use tokio::sync::mpsc::Sender;
+
+#[derive(Deserialize)]
+struct ReqData {
+    /// OPML 文件内容
+    content: String,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "result", content = "detail")]
+enum ImportResult {
+    Added(usize),
+    Duplicate,
+    Failed(String),
+}
+
+#[derive(Serialize)]
+struct FeedReport {
+    url: String,
+    title: String,
+    #[serde(flatten)]
+    result: ImportResult,
+}
+
+/// 从 OPML 文件导入订阅，逐个返回导入结果，单个订阅失败不影响其他订阅。
+/// `nekodl:settings` 与 `set_rss_polling`、`set_rss_tags` 使用相同的检查
+#[handler]
+pub async fn import_opml(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<Vec<FeedReport>>, Error> {
+    let data: ReqData = req.parse_json().await?;
+    let feeds = parse_opml(&data.content)?;
+
+    // 已存在的订阅地址，用于查重
+    let mut known_urls = HashSet::new();
+    for rss in DataBaseLock::from_depot(depot)?
+        .read()
+        .await
+        .rss_list
+        .values()
+    {
+        known_urls.insert(rss.read().await.url.clone());
+    }
+
+    let sender = Sender::<Event>::from_depot(depot)?.clone();
+    let owner = CurrentUser::from_depot(depot)?.name.clone();
+    let config = ConfigLock::from_depot(depot)?.read().await.clone();
+    let mut reports = Vec::new();
+    for feed in feeds {
+        let feed = match feed {
+            Ok(feed) => feed,
+            Err(invalid) => {
+                reports.push(FeedReport {
+                    url: invalid.url,
+                    title: invalid.title,
+                    result: ImportResult::Failed(invalid.error),
+                });
+                continue;
+            }
+        };
+        if let Some(settings) = &feed.settings {
+            let checked = check_update_interval(settings.update_interval)
+                .and_then(|_| check_category(&config, settings.category.as_deref()));
+            if let Err(e) = checked {
+                reports.push(FeedReport {
+                    url: feed.url,
+                    title: feed.title,
+                    result: ImportResult::Failed(e.to_string()),
+                });
+                continue;
+            }
+        }
+        if !known_urls.insert(feed.url.clone()) {
+            reports.push(FeedReport {
+                url: feed.url,
+                title: feed.title,
+                result: ImportResult::Duplicate,
+            });
+            continue;
+        }
+        // 获取频道信息以确认订阅可用
+        let channel = match fetch_channel(&feed.url).await {
+            Ok(channel) => channel,
+            Err(e) => {
+                reports.push(FeedReport {
+                    url: feed.url,
+                    title: feed.title,
+                    result: ImportResult::Failed(e.to_string()),
+                });
+                continue;
+            }
+        };
+        let id = {
+            let mut db = DataBaseLock::from_depot(depot)?.write().await;
+            db.rss_id_index += 1;
+            db.rss_id_index
+        };
+        let mut rss = Rss::new(
+            id,
+            feed.url.clone(),
+            channel.title().to_owned(),
+            channel.description().to_owned(),
+        );
+        if let Some(settings) = &feed.settings {
+            settings.apply(&mut rss);
+        }
+        rss.owner = Some(owner.clone());
+        let title = rss.title.clone();
+        sender.send(Event::AddRss(rss)).await?;
+        reports.push(FeedReport {
+            url: feed.url,
+            title,
+            result: ImportResult::Added(id),
+        });
+    }
+    Ok(ApiResponse::ok(reports))
+}
@@ -0,0 +1,44 @@
+use crate::api::*;
+use crate::event::Event;
+use crate::rss::RssItemStatus;
+use salvo::prelude::*;
+use serde::Deserialize;
+use tokio::sync::mpsc::Sender;
+
+#[derive(Deserialize)]
+struct ReqData {
+    rss_id: usize,
+}
+
+/// 将订阅中的所有未读条目标记为已读，返回修改的条目数量
+#[handler]
+pub async fn mark_rss_read(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<usize>, Error> {
+    let data: ReqData = req.parse_json().await?;
+    let mut count = 0;
+    {
+        let db = DataBaseLock::from_depot(depot)?.read().await;
+        let rss = db
+            .rss_list
+            .get(&data.rss_id)
+            .context("Rss not found")?
+            .read()
+            .await;
+        check_owner(depot, &rss)?;
+        for item in rss.items.iter() {
+            let mut item = item.write().await;
+            if item.status == RssItemStatus::Unread {
+                item.status = RssItemStatus::Read;
+                count += 1;
+            }
+        }
+    }
+    if count > 0 {
+        Sender::<Event>::from_depot(depot)?
+            .send(Event::SaveRss(data.rss_id))
+            .await?;
+    }
+    Ok(ApiResponse::ok(count))
+}
@@ -1,4 +1,14 @@
 pub mod add_rss_sub;
+pub mod bulk_set_item_status;
+pub mod download_item_torrent;
+pub mod export_opml;
+pub mod get_item_torrent;
 pub mod get_rss_info;
 pub mod get_rss_list;
-mod get_item_torrent;
+pub mod import_opml;
+pub mod mark_rss_read;
+pub mod search_items;
+pub mod set_item_status;
+pub mod set_rss_polling;
+pub mod set_rss_retention;
+pub mod set_rss_tags;
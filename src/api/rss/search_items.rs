@@ -0,0 +1,102 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::api::*;
+use crate::rss::{ItemFilter, RssItem, RssItemStatus};
+use salvo::prelude::*;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct ReqData {
+    query: String,
+    rss_id: Option<usize>,
+    tag: Option<String>,
+    status: Option<RssItemStatus>,
+    /// 发布时间下限，Unix 时间戳（秒）
+    after: Option<u64>,
+    /// 发布时间上限，Unix 时间戳（秒）
+    before: Option<u64>,
+    /// 页码，从 0 开始
+    #[serde(default)]
+    page: usize,
+    #[serde(default = "default_page_size")]
+    page_size: usize,
+}
+
+fn default_page_size() -> usize {
+    20
+}
+
+#[derive(Serialize)]
+struct SearchHit {
+    rss_id: usize,
+    rss_title: String,
+    score: f64,
+    item: RssItem,
+}
+
+#[derive(Serialize)]
+struct Resp {
+    /// 满足条件的结果总数
+    total: usize,
+    hits: Vec<SearchHit>,
+}
+
+/// 在所有订阅的条目中全文搜索，结果按相关度排序并分页
+#[handler]
+pub async fn search_items(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<Resp>, Error> {
+    let data: ReqData = req.parse_json().await?;
+    let page_size = data.page_size.clamp(1, 100);
+    let after = data.after.map(|t| UNIX_EPOCH + Duration::from_secs(t));
+    let before = data.before.map(|t| UNIX_EPOCH + Duration::from_secs(t));
+    let filter = ItemFilter {
+        rss_id: data.rss_id,
+        tag: data.tag,
+        status: data.status,
+        ..Default::default()
+    };
+    let results = StateLock::from_depot(depot)?
+        .read()
+        .await
+        .search_index
+        .clone()
+        .read()
+        .await
+        .search(&data.query);
+
+    let db = DataBaseLock::from_depot(depot)?.read().await;
+    let mut total = 0;
+    let mut hits = Vec::new();
+    for ((rss_id, item_id), score) in results {
+        let Some(rss) = db.rss_list.get(&rss_id) else {
+            continue;
+        };
+        let rss = rss.read().await;
+        if !filter.matches_rss(&rss) {
+            continue;
+        }
+        let Some(item) = rss.find_item(item_id).await else {
+            continue;
+        };
+        let item = item.read().await;
+        if !filter.matches_item(&item) {
+            continue;
+        }
+        let time = item.publish_time().unwrap_or(SystemTime::UNIX_EPOCH);
+        if after.is_some_and(|t| time < t) || before.is_some_and(|t| time > t) {
+            continue;
+        }
+        total += 1;
+        if total > data.page * page_size && hits.len() < page_size {
+            hits.push(SearchHit {
+                rss_id,
+                rss_title: rss.title.clone(),
+                score,
+                item: item.clone(),
+            });
+        }
+    }
+    Ok(ApiResponse::ok(Resp { total, hits }))
+}
@@ -0,0 +1,44 @@
+use crate::api::*;
+use crate::event::Event;
+use crate::rss::RssItemStatus;
+use salvo::prelude::*;
+use serde::Deserialize;
+use tokio::sync::mpsc::Sender;
+
+#[derive(Deserialize)]
+struct ReqData {
+    rss_id: usize,
+    item_id: usize,
+    status: RssItemStatus,
+}
+
+/// 修改单个条目的状态（已读、未读、忽略）
+#[handler]
+pub async fn set_item_status(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<bool>, Error> {
+    let data: ReqData = req.parse_json().await?;
+    let changed = {
+        let db = DataBaseLock::from_depot(depot)?.read().await;
+        let rss = db
+            .rss_list
+            .get(&data.rss_id)
+            .context("Rss not found")?
+            .read()
+            .await;
+        check_owner(depot, &rss)?;
+        let item = rss
+            .find_item(data.item_id)
+            .await
+            .context("Item not found")?;
+        let changed = item.write().await.set_user_status(data.status)?;
+        changed
+    };
+    if changed {
+        Sender::<Event>::from_depot(depot)?
+            .send(Event::SaveRss(data.rss_id))
+            .await?;
+    }
+    Ok(ApiResponse::ok(changed))
+}
@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+use crate::api::*;
+use crate::event::Event;
+use crate::rss::check_update_interval;
+use salvo::prelude::*;
+use serde::Deserialize;
+use tokio::sync::mpsc::Sender;
+
+#[derive(Deserialize)]
+struct ReqData {
+    rss_id: usize,
+    /// 更新间隔，单位为秒
+    update_interval: Option<u64>,
+    adaptive_polling: bool,
+}
+
+/// 设置订阅的更新间隔，以及是否根据推断出的放送时间调整轮询间隔
+#[handler]
+pub async fn set_rss_polling(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<()>, Error> {
+    let data: ReqData = req.parse_json().await?;
+    check_update_interval(data.update_interval)?;
+    {
+        let db = DataBaseLock::from_depot(depot)?.read().await;
+        let mut rss = db
+            .rss_list
+            .get(&data.rss_id)
+            .context("Rss not found")?
+            .write()
+            .await;
+        check_owner(depot, &rss)?;
+        if let Some(interval) = data.update_interval {
+            rss.update_interval = Duration::from_secs(interval);
+        }
+        rss.adaptive_polling = data.adaptive_polling;
+    }
+    Sender::<Event>::from_depot(depot)?
+        .send(Event::SaveRss(data.rss_id))
+        .await?;
+    Ok(ApiResponse::ok(()))
+}
@@ -0,0 +1,49 @@
+use std::time::SystemTime;
+
+use crate::api::*;
+use crate::event::Event;
+use crate::retention::RetentionPolicy;
+use salvo::prelude::*;
+use serde::Deserialize;
+use tokio::sync::mpsc::Sender;
+
+#[derive(Deserialize)]
+struct ReqData {
+    rss_id: usize,
+    retention: RetentionPolicy,
+}
+
+/// 设置订阅的条目保留策略，并立即按新策略删除条目，返回删除的条目数量
+#[handler]
+pub async fn set_rss_retention(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<usize>, Error> {
+    let data: ReqData = req.parse_json().await?;
+    let search_index = StateLock::from_depot(depot)?
+        .read()
+        .await
+        .search_index
+        .clone();
+    let pruned = {
+        let db = DataBaseLock::from_depot(depot)?.read().await;
+        let mut rss = db
+            .rss_list
+            .get(&data.rss_id)
+            .context("Rss not found")?
+            .write()
+            .await;
+        check_owner(depot, &rss)?;
+        rss.retention = data.retention;
+        let pruned = rss.prune(SystemTime::now()).await;
+        let mut search_index = search_index.write().await;
+        for id in pruned.iter() {
+            search_index.remove((rss.id, *id));
+        }
+        pruned.len()
+    };
+    Sender::<Event>::from_depot(depot)?
+        .send(Event::SaveRss(data.rss_id))
+        .await?;
+    Ok(ApiResponse::ok(pruned))
+}
@@ -0,0 +1,41 @@
+use crate::api::*;
+use crate::event::Event;
+use crate::rss::check_category;
+use salvo::prelude::*;
+use serde::Deserialize;
+use tokio::sync::mpsc::Sender;
+
+#[derive(Deserialize)]
+struct ReqData {
+    rss_id: usize,
+    tags: Vec<String>,
+    category: Option<String>,
+}
+
+/// 设置订阅的标签和分类，分类必须已经存在
+#[handler]
+pub async fn set_rss_tags(depot: &mut Depot, req: &mut Request) -> Result<ApiResponse<()>, Error> {
+    let mut data: ReqData = req.parse_json().await?;
+    check_category(
+        &*ConfigLock::from_depot(depot)?.read().await,
+        data.category.as_deref(),
+    )?;
+    data.tags.retain(|tag| !tag.trim().is_empty());
+    data.tags.dedup();
+    {
+        let db = DataBaseLock::from_depot(depot)?.read().await;
+        let mut rss = db
+            .rss_list
+            .get(&data.rss_id)
+            .context("Rss not found")?
+            .write()
+            .await;
+        check_owner(depot, &rss)?;
+        rss.tags = data.tags;
+        rss.category = data.category;
+    }
+    Sender::<Event>::from_depot(depot)?
+        .send(Event::SaveRss(data.rss_id))
+        .await?;
+    Ok(ApiResponse::ok(()))
+}
@@ -0,0 +1,27 @@
+use crate::api::*;
+use crate::series::{fetch_episode as fetch, EpisodeSource};
+use salvo::prelude::*;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct ReqData {
+    id: usize,
+    episode: u32,
+}
+
+/// 从剧集关联的订阅中下载指定的一集，返回被下载的条目，只能用于自己创建的剧集
+#[handler]
+pub async fn fetch_episode(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<EpisodeSource>, Error> {
+    let data: ReqData = req.parse_json().await?;
+    let state = StateLock::from_depot(depot)?.clone();
+    let config = ConfigLock::from_depot(depot)?.clone();
+    let db = DataBaseLock::from_depot(depot)?.read().await;
+    let series = db.series_list.get(&data.id).context("Series not found")?;
+    check_series_owner(depot, series)?;
+    Ok(ApiResponse::ok(
+        fetch(series, data.episode, &db, &state, &config).await?,
+    ))
+}
@@ -0,0 +1,22 @@
+use crate::api::*;
+use crate::series::{analyze, collect_episodes, SeriesReport};
+use salvo::prelude::*;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct ReqData {
+    id: usize,
+}
+
+/// 获取剧集的各集下载情况，包括缺失和重复的集数
+#[handler]
+pub async fn get_series_info(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<SeriesReport>, Error> {
+    let data: ReqData = req.parse_json().await?;
+    let db = DataBaseLock::from_depot(depot)?.read().await;
+    let series = db.series_list.get(&data.id).context("Series not found")?;
+    let episodes = collect_episodes(series, &db.rss_list).await;
+    Ok(ApiResponse::ok(analyze(series.clone(), episodes)))
+}
@@ -0,0 +1,17 @@
+use crate::api::*;
+use crate::series::Series;
+use salvo::prelude::*;
+
+/// 获取所有剧集
+#[handler]
+pub async fn get_series_list(depot: &mut Depot) -> Result<ApiResponse<Vec<Series>>, Error> {
+    let mut series: Vec<Series> = DataBaseLock::from_depot(depot)?
+        .read()
+        .await
+        .series_list
+        .values()
+        .cloned()
+        .collect();
+    series.sort_by_key(|s| s.id);
+    Ok(ApiResponse::ok(series))
+}
@@ -0,0 +1,5 @@
+pub mod fetch_episode;
+pub mod get_series_info;
+pub mod get_series_list;
+pub mod remove_series;
+pub mod set_series;
@@ -0,0 +1,26 @@
+use crate::api::*;
+use crate::event::Event;
+use salvo::prelude::*;
+use serde::Deserialize;
+use tokio::sync::mpsc::Sender;
+
+#[derive(Deserialize)]
+struct ReqData {
+    id: usize,
+}
+
+/// 删除剧集，关联的订阅和条目不受影响
+#[handler]
+pub async fn remove_series(depot: &mut Depot, req: &mut Request) -> Result<ApiResponse<()>, Error> {
+    let data: ReqData = req.parse_json().await?;
+    {
+        let mut db = DataBaseLock::from_depot(depot)?.write().await;
+        let series = db.series_list.get(&data.id).context("Series not found")?;
+        check_series_owner(depot, series)?;
+        db.series_list.remove(&data.id);
+    }
+    Sender::<Event>::from_depot(depot)?
+        .send(Event::SaveDatabase)
+        .await?;
+    Ok(ApiResponse::ok(()))
+}
@@ -0,0 +1,71 @@
+use crate::api::*;
+use crate::event::Event;
+use crate::series::Series;
+use salvo::prelude::*;
+use serde::Deserialize;
+use tokio::sync::mpsc::Sender;
+
+#[derive(Deserialize)]
+struct ReqData {
+    /// 为空时创建新的剧集
+    id: Option<usize>,
+    name: String,
+    rss_ids: Vec<usize>,
+    #[serde(default)]
+    keywords: Vec<String>,
+    season: Option<u32>,
+    total_episodes: Option<u32>,
+    #[serde(default)]
+    auto_fill: bool,
+}
+
+/// 创建或修改剧集，返回剧集 ID。
+/// 只能修改自己创建的剧集，关联的订阅也必须是当前用户可以修改的订阅。
+#[handler]
+pub async fn set_series(depot: &mut Depot, req: &mut Request) -> Result<ApiResponse<usize>, Error> {
+    let data: ReqData = req.parse_json().await?;
+    let id = {
+        let mut db = DataBaseLock::from_depot(depot)?.write().await;
+        for rss_id in data.rss_ids.iter() {
+            let rss = db
+                .rss_list
+                .get(rss_id)
+                .context(format!("Rss {} not found", rss_id))?
+                .read()
+                .await;
+            check_owner(depot, &rss)?;
+        }
+        let (id, owner) = match data.id {
+            Some(id) => {
+                let series = db.series_list.get(&id).context("Series not found")?;
+                check_series_owner(depot, series)?;
+                (id, series.owner.clone())
+            }
+            None => {
+                db.series_id_index += 1;
+                (
+                    db.series_id_index,
+                    Some(CurrentUser::from_depot(depot)?.name.clone()),
+                )
+            }
+        };
+        db.series_list.insert(
+            id,
+            Series {
+                id,
+                name: data.name,
+                rss_ids: data.rss_ids,
+                keywords: data.keywords,
+                season: data.season,
+                total_episodes: data.total_episodes,
+                auto_fill: data.auto_fill,
+                owner,
+            },
+        );
+        id
+    };
+    Sender::<Event>::from_depot(depot)?
+        .send(Event::SaveDatabase)
+        .await?;
+    Ok(ApiResponse::ok(id))
+}
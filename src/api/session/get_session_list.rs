@@ -0,0 +1,42 @@
+use crate::api::*;
+use crate::session::Session;
+use salvo::prelude::*;
+
+#[derive(Serialize)]
+struct Resp {
+    #[serde(flatten)]
+    session: Session,
+    /// 是否为发出请求的会话
+    current: bool,
+}
+
+/// 获取当前用户所有未过期的会话，按最后活动时间从新到旧排列
+#[handler]
+pub async fn get_session_list(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<Vec<Resp>>, Error> {
+    let options = ConfigLock::from_depot(depot)?
+        .read()
+        .await
+        .session_options
+        .clone();
+    let name = CurrentUser::from_depot(depot)?.name.clone();
+    let mut state = StateLock::from_depot(depot)?.write().await;
+    state.sessions.prune(&options);
+    let current = state
+        .sessions
+        .get(request_token(req))
+        .map(|session| session.id.clone());
+    Ok(ApiResponse::ok(
+        state
+            .sessions
+            .list(&name)
+            .into_iter()
+            .map(|session| Resp {
+                current: current.as_ref() == Some(&session.id),
+                session,
+            })
+            .collect(),
+    ))
+}
@@ -0,0 +1,14 @@
+use crate::api::*;
+use salvo::prelude::*;
+
+/// 注销当前会话
+#[handler]
+pub async fn logout(depot: &mut Depot, req: &mut Request) -> Result<ApiResponse<()>, Error> {
+    StateLock::from_depot(depot)?
+        .write()
+        .await
+        .sessions
+        .remove(request_token(req));
+    save_sessions(depot).await?;
+    Ok(ApiResponse::ok(()))
+}
@@ -0,0 +1,15 @@
+use crate::api::*;
+use salvo::prelude::*;
+
+/// 注销当前用户的所有会话，包括当前会话
+#[handler]
+pub async fn logout_all(depot: &mut Depot) -> Result<ApiResponse<()>, Error> {
+    let name = CurrentUser::from_depot(depot)?.name.clone();
+    StateLock::from_depot(depot)?
+        .write()
+        .await
+        .sessions
+        .remove_user(&name);
+    save_sessions(depot).await?;
+    Ok(ApiResponse::ok(()))
+}
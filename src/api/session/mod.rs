@@ -0,0 +1,4 @@
+pub mod get_session_list;
+pub mod logout;
+pub mod logout_all;
+pub mod revoke_session;
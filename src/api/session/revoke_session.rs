@@ -0,0 +1,28 @@
+use crate::api::*;
+use salvo::prelude::*;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct ReqData {
+    id: String,
+}
+
+/// 按会话标识注销当前用户的会话
+#[handler]
+pub async fn revoke_session(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<()>, Error> {
+    let data: ReqData = req.parse_json().await?;
+    let name = CurrentUser::from_depot(depot)?.name.clone();
+    if !StateLock::from_depot(depot)?
+        .write()
+        .await
+        .sessions
+        .remove_by_id(&name, &data.id)
+    {
+        return Err(anyhow!("Session not found").into());
+    }
+    save_sessions(depot).await?;
+    Ok(ApiResponse::ok(()))
+}
@@ -0,0 +1,27 @@
+use crate::api::*;
+use salvo::prelude::*;
+
+#[derive(Serialize)]
+struct UserInfo {
+    username: String,
+    role: Role,
+    /// 是否为配置中的内置账户
+    builtin: bool,
+}
+
+/// 获取所有用户，内置账户在最前
+#[handler]
+pub async fn get_user_list(depot: &mut Depot) -> Result<ApiResponse<Vec<UserInfo>>, Error> {
+    let config = ConfigLock::from_depot(depot)?.read().await;
+    let mut users = vec![UserInfo {
+        username: config.username.clone(),
+        role: Role::Admin,
+        builtin: true,
+    }];
+    users.extend(config.users.iter().map(|(name, user)| UserInfo {
+        username: name.clone(),
+        role: user.role,
+        builtin: false,
+    }));
+    Ok(ApiResponse::ok(users))
+}
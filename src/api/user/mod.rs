@@ -0,0 +1,3 @@
+pub mod get_user_list;
+pub mod remove_user;
+pub mod set_user;
@@ -0,0 +1,30 @@
+use crate::api::*;
+use salvo::prelude::*;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct ReqData {
+    username: String,
+}
+
+/// 删除用户并注销其会话和 API 密钥，用户添加的订阅保留，之后只有管理员可以修改
+#[handler]
+pub async fn remove_user(depot: &mut Depot, req: &mut Request) -> Result<ApiResponse<()>, Error> {
+    let data: ReqData = req.parse_json().await?;
+    ConfigLock::from_depot(depot)?
+        .write()
+        .await
+        .users
+        .remove(&data.username)
+        .context("User")?;
+    {
+        let mut state = StateLock::from_depot(depot)?.write().await;
+        state.sessions.remove_user(&data.username);
+        state
+            .api_keys
+            .retain_users(|username| username != data.username);
+    }
+    save_api_keys(depot).await?;
+    save_sessions(depot).await?;
+    Ok(ApiResponse::ok(()))
+}
@@ -0,0 +1,58 @@
+use crate::api::*;
+use crate::password;
+use crate::user::User;
+use salvo::prelude::*;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct ReqData {
+    username: String,
+    role: Role,
+    /// 为空时保留原密码，新用户必须设置密码
+    #[serde(default)]
+    password: Option<String>,
+}
+
+/// 添加或修改用户，修改密码后该用户的会话失效。
+/// 内置账户只能通过配置文件或 `change_password` 修改。
+#[handler]
+pub async fn set_user(depot: &mut Depot, req: &mut Request) -> Result<ApiResponse<()>, Error> {
+    let data: ReqData = req.parse_json().await?;
+    let password = data.password.filter(|password| !password.is_empty());
+    if data.username.is_empty() {
+        return Err(anyhow!("Invalid username {}", data.username).into());
+    }
+    // 在持有配置锁之前计算哈希
+    let hash = match password {
+        Some(password) => Some(password::spawn_hash(password).await?),
+        None => None,
+    };
+    let mut config = ConfigLock::from_depot(depot)?.write().await;
+    if data.username == config.username {
+        return Err(anyhow!("Invalid username {}", data.username).into());
+    }
+    match (config.users.get_mut(&data.username), hash) {
+        (Some(user), hash) => {
+            user.role = data.role;
+            if let Some(hash) = hash {
+                user.password = hash;
+                StateLock::from_depot(depot)?
+                    .write()
+                    .await
+                    .sessions
+                    .remove_user(&data.username);
+            }
+        }
+        (None, Some(password)) => {
+            config.users.insert(
+                data.username,
+                User {
+                    password,
+                    role: data.role,
+                },
+            );
+        }
+        (None, None) => return Err(anyhow!("Password is required for new user").into()),
+    }
+    Ok(ApiResponse::ok(()))
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    session::now,
+    utils::{rand_str, sha256},
+};
+
+/// API 密钥的前缀，便于在日志和脚本中识别
+const KEY_PREFIX: &str = "nk_";
+
+/// API 密钥可以访问的接口范围，账户和管理接口只能通过登录访问
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// 查看订阅和串流文件
+    Read,
+    /// 添加和修改订阅
+    Feeds,
+    /// 创建下载任务
+    Downloads,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct ApiKey {
+    /// 公开的密钥标识，用于列出和吊销密钥，不能用于认证
+    pub id: String,
+    pub name: String,
+    /// 创建密钥的用户，密钥的权限不超过该用户的角色
+    pub username: String,
+    /// 为空时不限制范围，`feeds` 和 `downloads` 包含 `read`
+    pub scopes: Vec<Scope>,
+    pub created_at: u64,
+    pub last_used: Option<u64>,
+}
+
+impl ApiKey {
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.scopes.is_empty() || scope == Scope::Read || self.scopes.contains(&scope)
+    }
+}
+
+/// 所有 API 密钥，以密钥的 SHA-256 为键，保存的数据中不包含密钥本身
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ApiKeys {
+    keys: HashMap<String, ApiKey>,
+}
+
+impl ApiKeys {
+    /// 创建密钥，返回密钥和其信息，密钥只在创建时返回一次
+    pub fn create(&mut self, username: &str, name: String, scopes: Vec<Scope>) -> (String, ApiKey) {
+        let key = format!("{}{}", KEY_PREFIX, rand_str(40));
+        let info = ApiKey {
+            id: rand_str(16),
+            name,
+            username: username.to_owned(),
+            scopes,
+            created_at: now(),
+            last_used: None,
+        };
+        self.keys.insert(sha256(&key), info.clone());
+        (key, info)
+    }
+
+    /// 校验密钥并记录使用时间
+    pub fn validate(&mut self, key: &str) -> Option<&ApiKey> {
+        let info = self.keys.get_mut(&sha256(key))?;
+        info.last_used = Some(now());
+        Some(info)
+    }
+
+    /// 用户的密钥，按创建时间排列
+    pub fn list(&self, username: &str) -> Vec<ApiKey> {
+        let mut list: Vec<ApiKey> = self
+            .keys
+            .values()
+            .filter(|info| info.username == username)
+            .cloned()
+            .collect();
+        list.sort_by_key(|info| info.created_at);
+        list
+    }
+
+    pub fn revoke(&mut self, username: &str, id: &str) -> bool {
+        let len = self.keys.len();
+        self.keys
+            .retain(|_, info| info.username != username || info.id != id);
+        self.keys.len() != len
+    }
+
+    /// 只保留 `f` 返回 `true` 的用户的密钥
+    pub fn retain_users(&mut self, f: impl Fn(&str) -> bool) {
+        self.keys.retain(|_, info| f(&info.username));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_api_keys() {
+        let mut keys = ApiKeys::default();
+        let (key, info) = keys.create("alice", "player".to_owned(), vec![Scope::Feeds]);
+        assert!(key.starts_with(KEY_PREFIX));
+        assert!(!serde_json::to_string(&keys).unwrap().contains(&key));
+        assert!(info.allows(Scope::Read) && info.allows(Scope::Feeds));
+        assert!(!info.allows(Scope::Downloads));
+
+        assert!(keys.validate(&key).unwrap().last_used.is_some());
+        assert!(keys.validate("nk_invalid").is_none());
+        assert_eq!(keys.list("alice").len(), 1);
+        assert!(keys.list("bob").is_empty());
+        assert!(!keys.revoke("bob", &info.id));
+        assert!(keys.revoke("alice", &info.id));
+        assert!(keys.validate(&key).is_none());
+    }
+}
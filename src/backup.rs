@@ -0,0 +1,186 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// 配置文件和数据库的备份设置
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct BackupOptions {
+    /// 保留的备份数量，为 0 时不备份
+    pub count: usize,
+    /// 数据库备份的间隔，单位为秒
+    pub interval: u64,
+}
+
+impl Default for BackupOptions {
+    fn default() -> Self {
+        Self {
+            count: 3,
+            interval: 3600,
+        }
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path: OsString = path.into();
+    path.push(suffix);
+    path.into()
+}
+
+/// 第 `n` 个备份的路径，1 为最新的备份
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    with_suffix(path, &format!(".bak.{}", n))
+}
+
+/// 已有的备份，从新到旧排列
+pub fn backups(path: &Path) -> Vec<PathBuf> {
+    (1..)
+        .map(|n| backup_path(path, n))
+        .take_while(|path| path.exists())
+        .collect()
+}
+
+/// 同步文件所在的目录，确保重命名已经写入磁盘
+fn sync_dir(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        File::open(dir)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// 先写入临时文件并同步到磁盘，再重命名为目标文件，写入中途崩溃不会留下不完整的文件
+pub fn atomic_write(path: &Path, data: &[u8]) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    write_atomic(path, data, &options)
+}
+
+/// 与 [`atomic_write`] 相同，但文件从创建时起只有所有者可以读写，用于私钥等文件
+pub fn atomic_write_private(path: &Path, data: &[u8]) -> Result<()> {
+    // 遗留的临时文件可能有更宽松的权限，删除后重新创建
+    match fs::remove_file(with_suffix(path, ".tmp")) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    write_atomic(path, data, &options)
+}
+
+fn write_atomic(path: &Path, data: &[u8], options: &OpenOptions) -> Result<()> {
+    let tmp = with_suffix(path, ".tmp");
+    let mut file = options.open(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&tmp, path)?;
+    sync_dir(path)
+}
+
+/// 将已经写好的 `new_backup` 作为最新的备份，其余备份依次后移，超出 `count` 的备份被删除
+pub fn rotate(path: &Path, new_backup: &Path, count: usize) -> Result<()> {
+    if count == 0 {
+        fs::remove_file(new_backup)?;
+        return Ok(());
+    }
+    for n in (1..count).rev() {
+        let from = backup_path(path, n);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, n + 1))?;
+        }
+    }
+    // 备份数量减少后删除多余的旧备份
+    let mut n = count + 1;
+    while backup_path(path, n).exists() {
+        fs::remove_file(backup_path(path, n))?;
+        n += 1;
+    }
+    fs::rename(new_backup, backup_path(path, 1))?;
+    sync_dir(path)
+}
+
+/// 写入文件，内容有变化时先将旧文件加入备份
+pub fn write_with_backup(path: &Path, data: &[u8], count: usize) -> Result<()> {
+    match fs::read(path) {
+        Ok(old) if old == data => return Ok(()),
+        Ok(old) if count > 0 => {
+            let tmp = with_suffix(path, ".bak.tmp");
+            atomic_write(&tmp, &old)?;
+            rotate(path, &tmp, count)?;
+        }
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    atomic_write(path, data)
+}
+
+/// 将损坏的文件重命名为 `<path>.corrupt`，以便之后手动检查
+pub fn set_aside(path: &Path) -> Result<PathBuf> {
+    let corrupt = with_suffix(path, ".corrupt");
+    fs::rename(path, &corrupt)?;
+    Ok(corrupt)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write_with_backup() {
+        let dir = std::env::temp_dir().join(format!("nekodl-backup-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        for i in 0..5 {
+            write_with_backup(&path, format!("{}", i).as_bytes(), 2).unwrap();
+            // 内容相同时不产生新的备份
+            write_with_backup(&path, format!("{}", i).as_bytes(), 2).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "4");
+        assert_eq!(
+            backups(&path),
+            vec![backup_path(&path, 1), backup_path(&path, 2)]
+        );
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), "3");
+        assert_eq!(fs::read_to_string(backup_path(&path, 2)).unwrap(), "2");
+
+        write_with_backup(&path, b"5", 1).unwrap();
+        assert_eq!(backups(&path), vec![backup_path(&path, 1)]);
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), "4");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_atomic_write_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("nekodl-private-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("key.pem");
+        fs::write(with_suffix(&path, ".tmp"), b"stale").unwrap();
+        atomic_write_private(&path, b"secret").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "secret");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
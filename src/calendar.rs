@@ -0,0 +1,298 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::{
+    episode::parse_title,
+    rss::{Rss, RssItem},
+    series::Series,
+    state::SerdeLockLayer,
+};
+
+const DAY: u64 = 24 * 3600;
+const WEEK: u64 = 7 * DAY;
+/// 用于推断放送时间的最近集数
+const SAMPLE_SIZE: usize = 4;
+/// 超过预计时间多久仍未发布视为延迟
+pub const LATE_GRACE: Duration = Duration::from_secs(6 * 3600);
+/// 预计发布时间前后的密集轮询窗口
+const WINDOW_BEFORE: Duration = Duration::from_secs(30 * 60);
+const WINDOW_AFTER: Duration = Duration::from_secs(12 * 3600);
+/// 窗口内的轮询间隔
+const FAST_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// 超过预计时间四周仍未发布，视为已停更或完结
+const STALE_AFTER: Duration = Duration::from_secs(4 * WEEK);
+
+/// 根据已发布集数推断出的放送规律
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Prediction {
+    pub last_episode: u32,
+    #[serde(with = "unix_secs")]
+    pub last_release: SystemTime,
+    pub next_episode: u32,
+    #[serde(with = "unix_secs")]
+    pub expected: SystemTime,
+    /// 每周放送时间在一周内的偏移（秒），以 UTC 周一 00:00 为起点
+    pub weekly_offset: u64,
+}
+
+impl Prediction {
+    /// 下一集是否已超过预计时间仍未发布
+    pub fn is_late(&self, now: SystemTime) -> bool {
+        now > self.expected + LATE_GRACE
+    }
+
+    /// 是否仍在连载，长期没有新一集时不再预测
+    pub fn is_active(&self, now: SystemTime) -> bool {
+        now < self.expected + STALE_AFTER
+    }
+
+    /// 按每周放送推算 `[start, end)` 内的各集及其预计时间，不超过总集数
+    pub fn project(
+        &self,
+        total_episodes: Option<u32>,
+        start: SystemTime,
+        end: SystemTime,
+    ) -> Vec<(u32, SystemTime)> {
+        let mut releases = Vec::new();
+        let mut episode = self.next_episode;
+        let mut expected = self.expected;
+        while expected < end && total_episodes.is_none_or(|total| episode <= total) {
+            if expected >= start {
+                releases.push((episode, expected));
+            }
+            episode += 1;
+            expected += Duration::from_secs(WEEK);
+        }
+        releases
+    }
+}
+
+mod unix_secs {
+    use serde::Serializer;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    pub fn serialize<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(
+            time.duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        )
+    }
+}
+
+fn secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 时间在一周内的偏移，Unix 纪元是周四，因此先偏移三天使周一为起点
+fn week_offset(time: u64) -> u64 {
+    (time + 3 * DAY) % WEEK
+}
+
+/// 两个周内偏移之间的差值，规范到半周以内
+fn offset_delta(a: u64, b: u64) -> i64 {
+    let delta = (a as i64 - b as i64).rem_euclid(WEEK as i64);
+    if delta > (WEEK / 2) as i64 {
+        delta - WEEK as i64
+    } else {
+        delta
+    }
+}
+
+/// 从每集的首次发布时间推断每周的放送时间和下一集的预计时间。
+/// 至少需要两集，且最近几集的间隔接近整周时才认为是周更。
+pub fn predict(releases: &BTreeMap<u32, SystemTime>) -> Option<Prediction> {
+    let recent: Vec<(u32, u64)> = releases
+        .iter()
+        .rev()
+        .take(SAMPLE_SIZE)
+        .map(|(episode, time)| (*episode, secs(*time)))
+        .collect();
+    if recent.len() < 2 {
+        return None;
+    }
+    // 相邻两集的间隔应为 (集数差 × 一周) ± 一天
+    for pair in recent.windows(2) {
+        let (later, earlier) = (pair[0], pair[1]);
+        let weeks = (later.0 - earlier.0) as u64;
+        let gap = later.1.saturating_sub(earlier.1);
+        if weeks == 0 || gap.abs_diff(weeks * WEEK) > DAY {
+            return None;
+        }
+    }
+    let (last_episode, last_release) = recent[0];
+    // 以最新一集为参照，取各集偏移的中位数，避免跨越周边界时出错
+    let reference = week_offset(last_release);
+    let mut deltas: Vec<i64> = recent
+        .iter()
+        .map(|(_, time)| offset_delta(week_offset(*time), reference))
+        .collect();
+    deltas.sort();
+    let median = deltas[deltas.len() / 2];
+    let weekly_offset = (reference as i64 + median).rem_euclid(WEEK as i64) as u64;
+    let expected = (last_release + WEEK).checked_add_signed(offset_delta(
+        weekly_offset,
+        week_offset(last_release + WEEK),
+    ))?;
+    Some(Prediction {
+        last_episode,
+        last_release: UNIX_EPOCH + Duration::from_secs(last_release),
+        next_episode: last_episode + 1,
+        expected: UNIX_EPOCH + Duration::from_secs(expected),
+        weekly_offset,
+    })
+}
+
+/// 计算距离下一次轮询的等待时间。
+/// 预计发布时间附近缩短轮询间隔，在窗口开始前提前醒来，发布后恢复正常间隔。
+pub fn next_poll_delay(
+    interval: Duration,
+    elapsed: Duration,
+    prediction: Option<&Prediction>,
+    now: SystemTime,
+) -> Duration {
+    let normal = interval.saturating_sub(elapsed);
+    let Some(prediction) = prediction else {
+        return normal;
+    };
+    let window_start = prediction.expected - WINDOW_BEFORE;
+    let window_end = prediction.expected + WINDOW_AFTER;
+    if now >= window_start && now <= window_end {
+        normal.min(FAST_INTERVAL.saturating_sub(elapsed))
+    } else if let Ok(until_window) = window_start.duration_since(now) {
+        normal.min(until_window)
+    } else {
+        normal
+    }
+}
+
+/// 每集的首次发布时间
+pub fn first_releases(
+    items: impl IntoIterator<Item = (u32, Option<u32>, Option<SystemTime>)>,
+) -> BTreeMap<u32, SystemTime> {
+    let mut releases: BTreeMap<u32, SystemTime> = BTreeMap::new();
+    for (first, last, time) in items {
+        let Some(time) = time else {
+            continue;
+        };
+        // 合集不能反映放送时间
+        if last.is_some_and(|last| last != first) {
+            continue;
+        }
+        releases
+            .entry(first)
+            .and_modify(|t| *t = (*t).min(time))
+            .or_insert(time);
+    }
+    releases
+}
+
+/// 订阅中各集的首次发布时间，用于按订阅调整轮询间隔
+pub async fn feed_releases(items: &[SerdeLockLayer<RssItem>]) -> BTreeMap<u32, SystemTime> {
+    let mut episodes = Vec::new();
+    for item in items {
+        let item = item.read().await;
+        let info = parse_title(&item.title);
+        if let Some(episode) = info.episode {
+            episodes.push((episode, info.episode_end, item.publish_time()));
+        }
+    }
+    first_releases(episodes)
+}
+
+/// 剧集各集的首次发布时间，只统计关联订阅中属于该剧集的条目
+pub async fn series_releases(
+    series: &Series,
+    rss_list: &HashMap<usize, SerdeLockLayer<Rss>>,
+) -> BTreeMap<u32, SystemTime> {
+    let mut episodes = Vec::new();
+    for rss_id in series.rss_ids.iter() {
+        let Some(rss) = rss_list.get(rss_id) else {
+            continue;
+        };
+        let rss = rss.read().await;
+        for item in rss.items.iter() {
+            let item = item.read().await;
+            let info = parse_title(&item.title);
+            if let Some(episode) = info.episode {
+                if series.matches(&item, &info) {
+                    episodes.push((episode, info.episode_end, item.publish_time()));
+                }
+            }
+        }
+    }
+    first_releases(episodes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// 2024-01-01 是周一
+    fn time(day: u64, hour: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1704067200 + day * DAY + hour * 3600)
+    }
+
+    #[test]
+    fn test_predict_weekly() {
+        // 每周六 15 点左右发布，第 3 集晚了一小时
+        let releases = BTreeMap::from([
+            (1, time(5, 15)),
+            (2, time(12, 15)),
+            (3, time(19, 16)),
+            (4, time(26, 15)),
+        ]);
+        let prediction = predict(&releases).unwrap();
+        assert_eq!(prediction.next_episode, 5);
+        assert_eq!(prediction.expected, time(33, 15));
+        assert_eq!(prediction.weekly_offset, 5 * DAY + 15 * 3600);
+        assert!(!prediction.is_late(time(33, 20)));
+        assert!(prediction.is_late(time(33, 22)));
+
+        let projected = prediction.project(Some(6), time(30, 0), time(60, 0));
+        assert_eq!(projected, vec![(5, time(33, 15)), (6, time(40, 15))]);
+    }
+
+    #[test]
+    fn test_predict_irregular() {
+        assert!(predict(&BTreeMap::from([(1, time(0, 0))])).is_none());
+        // 同一天发布多集，不是周更
+        let releases = BTreeMap::from([(1, time(0, 0)), (2, time(0, 1)), (3, time(0, 2))]);
+        assert!(predict(&releases).is_none());
+        // 跳过一集时按集数差计算间隔
+        let releases = BTreeMap::from([(1, time(0, 23)), (3, time(14, 23))]);
+        assert_eq!(predict(&releases).unwrap().expected, time(21, 23));
+    }
+
+    #[test]
+    fn test_next_poll_delay() {
+        let releases = BTreeMap::from([(1, time(0, 12)), (2, time(7, 12))]);
+        let prediction = predict(&releases).unwrap();
+        let hour = Duration::from_secs(3600);
+        // 远离预计时间时使用正常间隔
+        assert_eq!(
+            next_poll_delay(hour, Duration::ZERO, Some(&prediction), time(10, 0)),
+            hour
+        );
+        // 窗口开始前提前醒来
+        assert_eq!(
+            next_poll_delay(hour, Duration::ZERO, Some(&prediction), time(14, 11)),
+            Duration::from_secs(30 * 60)
+        );
+        // 窗口内缩短间隔
+        assert_eq!(
+            next_poll_delay(hour, Duration::ZERO, Some(&prediction), time(14, 13)),
+            FAST_INTERVAL
+        );
+        assert_eq!(
+            next_poll_delay(hour, hour, None, time(14, 13)),
+            Duration::ZERO
+        );
+    }
+}
@@ -0,0 +1,511 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{Map, Value};
+use tokio::{sync::RwLock, time::sleep};
+use tracing::{error, info, warn};
+
+use crate::{
+    listen,
+    state::{Config, State},
+};
+
+/// 检查配置文件是否被修改的间隔
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 环境变量的前缀，嵌套字段用 `__` 分隔，
+/// 例如 `NEKODL_METADATA_OPTIONS__CONCURRENCY` 对应 `metadata_options.concurrency`
+const ENV_PREFIX: &str = "NEKODL_";
+
+/// 输出配置时替换敏感字段的内容
+const MASK: &str = "******";
+
+/// 本进程最后一次写入配置文件的内容哈希，监视任务据此忽略自身的写入
+static WRITTEN: Mutex<Option<u64>> = Mutex::new(None);
+
+fn hash(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 记录写入配置文件的内容
+pub fn record_written(data: &[u8]) {
+    *WRITTEN.lock().unwrap() = Some(hash(data));
+}
+
+fn is_self_written(data: &[u8]) -> bool {
+    *WRITTEN.lock().unwrap() == Some(hash(data))
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "toml")
+}
+
+/// 按扩展名解析配置文件，`.toml` 为 TOML，其余为 JSON
+pub fn parse(path: &Path, content: &str) -> Result<Value> {
+    Ok(if is_toml(path) {
+        serde_json::to_value(toml::from_str::<toml::Value>(content)?)?
+    } else {
+        serde_json::from_str(content)?
+    })
+}
+
+/// 按扩展名序列化配置文件
+pub fn format(path: &Path, config: &Config) -> Result<String> {
+    Ok(if is_toml(path) {
+        toml::to_string_pretty(config)?
+    } else {
+        serde_json::to_string_pretty(config)?
+    })
+}
+
+/// 将 `overlay` 合并到 `base` 中，对象逐个字段合并，其余类型直接替换
+pub fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(base) => merge(base, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn get_path<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, key| value.get(key))
+}
+
+fn set_path(value: &mut Value, path: &[String], new: Value) {
+    let Some((last, parents)) = path.split_last() else {
+        *value = new;
+        return;
+    };
+    let mut value = value;
+    for key in parents {
+        if !value.is_object() {
+            *value = Value::Object(Map::new());
+        }
+        value = value
+            .as_object_mut()
+            .unwrap()
+            .entry(key.clone())
+            .or_insert(Value::Null);
+    }
+    if !value.is_object() {
+        *value = Value::Object(Map::new());
+    }
+    value.as_object_mut().unwrap().insert(last.clone(), new);
+}
+
+fn remove_path(value: &mut Value, path: &[String]) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let parent = parents
+        .iter()
+        .try_fold(value, |value, key| value.get_mut(key));
+    if let Some(Value::Object(map)) = parent {
+        map.remove(last);
+    }
+}
+
+/// 按字段原有的类型解析覆盖的值：字符串字段直接使用原文，
+/// 列表字段可以用逗号分隔，其余字段按 JSON 解析
+fn parse_value(raw: &str, current: Option<&Value>) -> Value {
+    match current {
+        Some(Value::String(_)) => Value::String(raw.to_owned()),
+        Some(Value::Array(_)) if !raw.trim_start().starts_with('[') => Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|s| Value::String(s.to_owned()))
+                .collect(),
+        ),
+        _ => serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_owned())),
+    }
+}
+
+/// 环境变量和命令行参数对配置的覆盖，按加入的顺序应用，后加入的优先。
+/// 覆盖的值只在运行时生效，保存配置文件时保留文件中原有的值。
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    entries: Vec<(Vec<String>, String)>,
+}
+
+impl Overrides {
+    /// 读取 `NEKODL_*` 环境变量
+    pub fn from_env() -> Self {
+        let mut overrides = Self::default();
+        overrides.add_vars(std::env::vars());
+        overrides
+    }
+
+    fn add_vars(&mut self, vars: impl Iterator<Item = (String, String)>) {
+        let mut vars: Vec<_> = vars
+            .filter_map(|(key, value)| {
+                let key = key.strip_prefix(ENV_PREFIX)?.to_lowercase();
+                Some((key.split("__").map(str::to_owned).collect(), value))
+            })
+            .collect();
+        // 环境变量的顺序不确定，按名称排序使结果稳定
+        vars.sort();
+        self.entries.extend(vars);
+    }
+
+    /// 加入一个覆盖，`key` 为用 `.` 分隔的字段路径
+    pub fn set(&mut self, key: &str, value: impl Into<String>) {
+        let path = key.split('.').map(str::to_owned).collect();
+        self.entries.push((path, value.into()));
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|(path, _)| path.join("."))
+            .collect()
+    }
+
+    /// 在配置上应用所有覆盖
+    pub fn apply(&self, config: &Config) -> Result<Config> {
+        if self.entries.is_empty() {
+            return Ok(config.clone());
+        }
+        let mut value = serde_json::to_value(config)?;
+        for (path, raw) in self.entries.iter() {
+            if value.get(&path[0]).is_none() {
+                warn!("Unknown config key {}", path.join("."));
+            }
+            let new = parse_value(raw, get_path(&value, path));
+            set_path(&mut value, path, new);
+        }
+        serde_json::from_value(value)
+            .with_context(|| format!("Invalid config override ({})", self.keys().join(", ")))
+    }
+
+    /// 将被覆盖的字段恢复为 `file` 中的值，得到应当写入配置文件的内容
+    pub fn strip(&self, config: &Config, file: &Config) -> Result<Config> {
+        if self.entries.is_empty() {
+            return Ok(config.clone());
+        }
+        let mut value = serde_json::to_value(config)?;
+        let file = serde_json::to_value(file)?;
+        for (path, _) in self.entries.iter() {
+            match get_path(&file, path) {
+                Some(original) => set_path(&mut value, path, original.clone()),
+                None => remove_path(&mut value, path),
+            }
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+/// 隐藏密码和密钥，用于输出配置
+pub fn masked(config: &Config) -> Config {
+    let mask = |value: &mut String| {
+        if !value.is_empty() {
+            *value = MASK.to_owned();
+        }
+    };
+    let mut config = config.clone();
+    mask(&mut config.password);
+    for user in config.users.values_mut() {
+        mask(&mut user.password);
+    }
+    if let Some(token) = config.token.as_mut() {
+        mask(token);
+    }
+    if let Some(feed_key) = config.feed_key.as_mut() {
+        mask(feed_key);
+    }
+    config
+}
+
+/// 修改后需要重启才能生效的字段
+pub fn restart_required(old: &Config, new: &Config) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if old.bind_address != new.bind_address {
+        fields.push("bind_address");
+    }
+    if old.unix_socket_mode != new.unix_socket_mode {
+        fields.push("unix_socket_mode");
+    }
+    if old.tls != new.tls {
+        fields.push("tls");
+    }
+    if old.db_path != new.db_path {
+        fields.push("db_path");
+    }
+    if old.session_path != new.session_path {
+        fields.push("session_path");
+    }
+    // 元数据任务启动时创建并发数量的信号量
+    if old.metadata_options.concurrency != new.metadata_options.concurrency {
+        fields.push("metadata_options.concurrency");
+    }
+    fields
+}
+
+/// 检查目录可以写入，目录不存在时检查最近的已存在的上级目录。
+/// 只读取元数据，不会创建目录或文件
+async fn check_writable_dir(path: &Path) -> Result<()> {
+    let mut dir = path;
+    loop {
+        match tokio::fs::metadata(dir).await {
+            Ok(meta) if !meta.is_dir() => {
+                return Err(anyhow!("{} is not a directory", dir.display()))
+            }
+            Ok(meta) if meta.permissions().readonly() => {
+                return Err(anyhow!("{} is read-only", dir.display()))
+            }
+            Ok(_) => return Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => match dir.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => dir = parent,
+                _ => return Ok(()),
+            },
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// 检查配置是否可用，返回所有问题
+pub async fn validate(config: &Config) -> Result<()> {
+    let mut problems = Vec::new();
+    if let Err(e) = listen::parse_bind_addresses(&config.bind_address) {
+        problems.push(format!("bind_address: {}", e));
+    }
+    if let Some(Err(e)) = config.unix_socket_mode.as_deref().map(listen::parse_mode) {
+        problems.push(format!("unix_socket_mode: {}", e));
+    }
+    if let Some(tls) = &config.tls {
+        for (field, path) in [
+            ("tls.cert_path", &tls.cert_path),
+            ("tls.key_path", &tls.key_path),
+        ] {
+            if path.is_empty() {
+                problems.push(format!("{}: must not be empty", field));
+            } else if !tls.self_signed && !Path::new(path).is_file() {
+                problems.push(format!("{}: {} does not exist", field, path));
+            }
+        }
+    }
+    if let Some(dir) = &config.webui_dir {
+        if !Path::new(dir).join("index.html").is_file() {
+            problems.push(format!("webui_dir: {} does not contain index.html", dir));
+        }
+    }
+    if config.username.is_empty() {
+        problems.push("username: must not be empty".to_owned());
+    }
+    for (name, user) in config.users.iter() {
+        if name.is_empty() || *name == config.username {
+            problems.push(format!(
+                "users.{}: name is empty or used by the built-in account",
+                name
+            ));
+        }
+        if user.password.is_empty() {
+            problems.push(format!("users.{}.password: must not be empty", name));
+        }
+    }
+    if config.password.is_empty() {
+        problems.push("password: must not be empty".to_owned());
+    }
+    if config.metadata_options.concurrency == 0 {
+        problems.push("metadata_options.concurrency: must be at least 1".to_owned());
+    }
+    if let Some(url) = &config.public_url {
+        if !(url.starts_with("http://") || url.starts_with("https://")) {
+            problems.push(format!("public_url: {} is not an http(s) url", url));
+        }
+    }
+    let mut dirs = vec![
+        ("output_path".to_owned(), PathBuf::from(&config.output_path)),
+        (
+            "session_path".to_owned(),
+            PathBuf::from(&config.session_path),
+        ),
+    ];
+    if let Some(parent) = Path::new(&config.db_path).parent() {
+        if !parent.as_os_str().is_empty() {
+            dirs.push(("db_path".to_owned(), parent.to_owned()));
+        }
+    }
+    for (name, category) in config.categories.iter() {
+        if let Some(path) = &category.output_path {
+            dirs.push((format!("categories.{}.output_path", name), path.into()));
+        }
+    }
+    for (field, dir) in dirs {
+        if let Err(e) = check_writable_dir(&dir).await {
+            problems.push(format!(
+                "{}: {} is not writable: {}",
+                field,
+                dir.display(),
+                e
+            ));
+        }
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("Invalid config: {}", problems.join("; ")))
+    }
+}
+
+/// 校验并应用新配置，返回需要重启才能生效的字段。
+/// 需要重启的字段同样会被写入，在下次启动时生效。
+pub async fn apply(
+    config: &RwLock<Config>,
+    state: &RwLock<State>,
+    new: Config,
+) -> Result<Vec<&'static str>> {
+    validate(&new).await?;
+    let mut config = config.write().await;
+    let restart = restart_required(&config, &new);
+    // 账户被删除或密码变更后，该账户的会话失效
+    let mut state = state.write().await;
+    state.sessions.retain_users(|username| {
+        let old = config.account(username).map(|(password, _)| password);
+        old.is_some() && new.account(username).map(|(password, _)| password) == old
+    });
+    // 账户被删除后，该账户的 API 密钥失效
+    state
+        .api_keys
+        .retain_users(|username| new.account(username).is_some());
+    // 其余字段在每次使用时读取，写入后立即生效
+    *config = new;
+    Ok(restart)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// 监视配置文件，文件被外部修改后重新加载
+pub async fn config_watch_task(
+    config: Arc<RwLock<Config>>,
+    state: Arc<RwLock<State>>,
+    path: PathBuf,
+    overrides: Overrides,
+) {
+    let mut last_modified = modified(&path);
+    loop {
+        sleep(WATCH_INTERVAL).await;
+        let current = modified(&path);
+        if current == last_modified {
+            continue;
+        }
+        last_modified = current;
+        // 忽略本进程写入的文件，即使内存中的配置已经被更新的修改覆盖
+        match tokio::fs::read(&path).await {
+            Ok(data) if is_self_written(&data) => continue,
+            Ok(_) => {}
+            Err(e) => {
+                error!("Failed to reload config {}: {:#}", path.display(), e);
+                continue;
+            }
+        }
+        let new = match Config::from_path(path.clone()).and_then(|new| overrides.apply(&new)) {
+            Ok(new) => new,
+            Err(e) => {
+                error!("Failed to reload config {}: {:#}", path.display(), e);
+                continue;
+            }
+        };
+        match apply(&config, &state, new).await {
+            Ok(restart) if restart.is_empty() => info!("Reloaded config {}", path.display()),
+            Ok(restart) => warn!(
+                "Reloaded config {}, restart required for: {}",
+                path.display(),
+                restart.join(", ")
+            ),
+            Err(e) => error!("Failed to reload config {}: {:#}", path.display(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_validate() {
+        let dir = std::env::temp_dir().join(format!("nekodl-config-{}", std::process::id()));
+        let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        let config = Config {
+            bind_address: "[::]:8001".to_owned(),
+            password: "hash".to_owned(),
+            db_path: path("nekodl.db"),
+            session_path: path("session"),
+            output_path: path("downloads"),
+            ..Config::default()
+        };
+        validate(&config).await.unwrap();
+        assert!(!dir.exists());
+
+        let mut new = config.clone();
+        new.bind_address = "localhost".to_owned();
+        new.password = String::new();
+        let err = validate(&new).await.unwrap_err().to_string();
+        assert!(err.contains("bind_address") && err.contains("password"));
+
+        new.bind_address = "127.0.0.1:9000".to_owned();
+        new.output_path = path("other");
+        assert_eq!(restart_required(&config, &new), vec!["bind_address"]);
+
+        std::fs::write(&dir, b"").unwrap();
+        let err = validate(&config).await.unwrap_err().to_string();
+        assert!(err.contains("output_path") && err.contains("not a directory"));
+        std::fs::remove_file(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_layers() {
+        let path = Path::new("config.toml");
+        let mut file = serde_json::to_value(Config::default()).unwrap();
+        let content = "password = \"hash\"\n[metadata_options]\nconcurrency = 2\n";
+        merge(&mut file, parse(path, content).unwrap());
+        let file: Config = serde_json::from_value(file).unwrap();
+        assert_eq!(file.metadata_options.concurrency, 2);
+        assert_eq!(file.username, "admin");
+
+        let mut overrides = Overrides::default();
+        overrides.add_vars(
+            [
+                ("NEKODL_METADATA_OPTIONS__CONCURRENCY", "8"),
+                ("NEKODL_PASSWORD", "123"),
+                ("PATH", "/usr/bin"),
+            ]
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value.to_owned())),
+        );
+        overrides.set("torrent_options.trackers", "udp://a, udp://b");
+        overrides.set("password", "456");
+        let config = overrides.apply(&file).unwrap();
+        assert_eq!(config.metadata_options.concurrency, 8);
+        assert_eq!(config.password, "456");
+        assert_eq!(config.torrent_options.trackers, vec!["udp://a", "udp://b"]);
+        assert_eq!(masked(&config).password, MASK);
+
+        let saved = overrides.strip(&config, &file).unwrap();
+        assert_eq!(saved.metadata_options.concurrency, 2);
+        assert_eq!(saved.password, "hash");
+        assert!(saved.torrent_options.trackers.is_empty());
+
+        let content = format(path, &saved).unwrap();
+        let parsed: Config = serde_json::from_value(parse(path, &content).unwrap()).unwrap();
+        assert_eq!(parsed.metadata_options.concurrency, 2);
+    }
+}
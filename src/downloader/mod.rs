@@ -2,7 +2,7 @@ use anyhow::Result;
 use salvo::async_trait;
 use std::sync::Arc;
 
-mod rqbit;
+pub mod rqbit;
 
 pub enum Source {
     HttpUrl(String),
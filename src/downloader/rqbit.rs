@@ -1,7 +1,6 @@
 use std::{
-    path::PathBuf,
     sync::{Arc, Weak},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, Context, Ok};
@@ -14,8 +13,10 @@ use tokio::{
 };
 
 use crate::{
-    rss::{RssItem, RssItemStatus},
-    state::{Config, State},
+    rss::{Rss, RssItem, RssItemStatus},
+    state::{Config, SeedingPolicy, State},
+    torrent::item_add_torrent,
+    torrent_cache::TorrentCache,
 };
 
 use super::{DownloadOptions, Downloader, Source};
@@ -24,8 +25,13 @@ pub enum Command {
     AddTorrentFile(Vec<u8>, oneshot::Sender<Arc<ManagedTorrent>>),
 }
 
+/// 通过 API 手动添加的下载任务
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DownloadTask {}
+pub struct DownloadTask {
+    pub id: usize,
+    /// 添加任务的用户，为空时只有管理员可以修改
+    pub owner: Option<String>,
+}
 
 pub async fn download_command_task(
     mut receiver: mpsc::Receiver<Command>,
@@ -54,24 +60,61 @@ pub async fn download_command_task(
     Ok(())
 }
 
+/// 等待做种策略满足，返回是否需要停止做种。策略为空时一直做种，直接返回 `false`
+async fn wait_seeding_policy(
+    handle: &ManagedTorrent,
+    policy: &SeedingPolicy,
+    item: &Weak<RwLock<RssItem>>,
+) -> bool {
+    if policy.ratio_limit.is_none() && policy.seed_time.is_none() {
+        return false;
+    }
+    let start = Instant::now();
+    loop {
+        if item.upgrade().is_none() {
+            return true;
+        }
+        if policy
+            .seed_time
+            .is_some_and(|t| start.elapsed() >= Duration::from_secs(t))
+        {
+            return true;
+        }
+        let stats = handle.stats();
+        if policy.ratio_limit.is_some_and(|ratio| {
+            stats.total_bytes > 0 && stats.uploaded_bytes as f64 >= stats.total_bytes as f64 * ratio
+        }) {
+            return true;
+        }
+        sleep(Duration::from_secs(30)).await;
+    }
+}
+
 pub async fn item_downaload_task(
     session: Arc<Session>,
     item: Weak<RwLock<RssItem>>,
-    title: String,
+    rss: Rss,
     config: Arc<RwLock<Config>>,
 ) -> anyhow::Result<()> {
-    let (link, output_path) = {
-        let item = item.upgrade().context("Can't upgrade item")?;
-        let item = item.read().await;
-        let mut output_path = PathBuf::from(&config.read().await.output_path);
-        output_path.push(title);
-        (item.link.clone(), output_path.to_string_lossy().into())
+    let (cache, output_path, trackers, seeding) = {
+        let config = config.read().await;
+        (
+            TorrentCache::new(&config.session_path),
+            rss.output_dir(&config).to_string_lossy().into(),
+            config.trackers(rss.category.as_deref()),
+            config
+                .category(rss.category.as_deref())
+                .map(|c| c.seeding.clone())
+                .unwrap_or_default(),
+        )
     };
+    // 种子文件优先从缓存中读取，即使源站已删除也可以重新添加
+    let add_torrent = item_add_torrent(&cache, &item).await?;
     let resp = session
         .add_torrent(
-            AddTorrent::Url(link.into()),
+            add_torrent,
             Some(AddTorrentOptions {
-                trackers: Some(config.read().await.torrent_options.trackers.clone()),
+                trackers: Some(trackers),
                 output_folder: Some(output_path),
                 ..Default::default()
             }),
@@ -81,9 +124,16 @@ pub async fn item_downaload_task(
     let weak = item.clone();
     tokio::select! {
         _ = handle.wait_until_completed() => {
-            let lock = item.upgrade().context("Can't upgrade item")?;
-            let mut guard = lock.write().await;
-            guard.status = RssItemStatus::Downloaded;
+            {
+                let lock = item.upgrade().context("Can't upgrade item")?;
+                let mut guard = lock.write().await;
+                guard.status = RssItemStatus::Downloaded;
+                guard.downloaded_time = Some(std::time::SystemTime::now());
+            }
+            // 按分类的做种策略停止做种，保留已下载的文件
+            if wait_seeding_policy(&handle, &seeding, &item).await {
+                session.delete(handle.shared.info_hash.into(), false).await?;
+            }
         }
         _ = async move {
             loop {
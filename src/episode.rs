@@ -0,0 +1,201 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+/// 从条目标题中解析出的剧集信息
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct EpisodeInfo {
+    /// 字幕组或发布组
+    pub group: Option<String>,
+    /// 作品名称
+    pub title: Option<String>,
+    pub season: Option<u32>,
+    pub episode: Option<u32>,
+    /// 合集的最后一集，单集时为空
+    pub episode_end: Option<u32>,
+    pub resolution: Option<String>,
+}
+
+static DECORATION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"★[^★]*★").unwrap());
+static GROUP: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*\[([^\]]+)\]").unwrap());
+static RESOLUTION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(2160|1080|720|480)p\b|\b(4k)\b|\b\d{3,4}[x×](2160|1080|720|480)\b").unwrap()
+});
+static SEASON_EPISODE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\bS(\d{1,2})E(\d{1,4})(?:v\d)?\b").unwrap());
+static RANGE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[\[\s](\d{1,4})\s*[-~]\s*(\d{1,4})(?:\s*(?:END|Fin|全集|精校))?[\]\s]").unwrap()
+});
+static CJK_EPISODE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"第\s*(\d{1,4})\s*[话話集]").unwrap());
+static DASH_EPISODE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\s-\s(\d{1,4})(?:v\d)?(?:\s|\[|\(|$)").unwrap());
+static BRACKET_EPISODE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[(\d{1,4})(?:v\d)?(?:\s*END)?\]").unwrap());
+static EP_EPISODE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\bEP?(\d{1,4})(?:v\d)?\b").unwrap());
+static SEASON: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\bS(\d{1,2})\b|\bSeason\s*(\d{1,2})\b|第([一二三四五六七八九十\d]{1,3})[季期]")
+        .unwrap()
+});
+
+/// 解析中文或阿拉伯数字，只支持一到九十九
+fn parse_number(s: &str) -> Option<u32> {
+    if let Ok(n) = s.parse() {
+        return Some(n);
+    }
+    let digit = |c: char| {
+        "一二三四五六七八九"
+            .chars()
+            .position(|d| d == c)
+            .map(|p| p as u32 + 1)
+    };
+    let chars: Vec<char> = s.chars().collect();
+    match chars.as_slice() {
+        ['十'] => Some(10),
+        ['十', b] => Some(10 + digit(*b)?),
+        [a, '十'] => Some(digit(*a)? * 10),
+        [a, '十', b] => Some(digit(*a)? * 10 + digit(*b)?),
+        [a] => digit(*a),
+        _ => None,
+    }
+}
+
+/// 四位数字且看起来像年份时不作为集数
+fn is_year(s: &str) -> bool {
+    s.len() == 4 && matches!(s.parse::<u32>(), Ok(1900..=2100))
+}
+
+/// 从标题中解析剧集信息，支持常见的字幕组命名格式，例如
+/// `[ANi] 葬送的芙莉蓮 - 05 [1080P][Baha][WEB-DL]` 与
+/// `【喵萌奶茶屋】★10月新番★[葬送的芙莉莲 / Sousou no Frieren][05][1080p]`
+pub fn parse_title(raw: &str) -> EpisodeInfo {
+    let title = raw.replace('【', "[").replace('】', "]");
+    // 去掉 `★10月新番★` 这类装饰文本
+    let title = DECORATION.replace_all(&title, "");
+    let mut info = EpisodeInfo::default();
+
+    // 标题开头的方括号通常是发布组
+    let body_start = match GROUP.captures(&title) {
+        Some(caps) => {
+            info.group = Some(caps[1].trim().to_owned());
+            caps.get(0).unwrap().end()
+        }
+        None => 0,
+    };
+    let body = &title[body_start..];
+
+    info.resolution = RESOLUTION
+        .captures(body)
+        .map(|caps| match caps.get(1).or(caps.get(3)) {
+            Some(height) => format!("{}p", height.as_str()),
+            None => "2160p".to_owned(),
+        });
+
+    // 集数所在位置，用于截取作品名称
+    let mut episode_start = None;
+    if let Some(caps) = SEASON_EPISODE.captures(body) {
+        info.season = caps[1].parse().ok();
+        info.episode = caps[2].parse().ok();
+        episode_start = caps.get(0).map(|m| m.start());
+    } else if let Some(caps) = RANGE
+        .captures_iter(body)
+        .find(|caps| !is_year(&caps[1]) && !is_year(&caps[2]))
+    {
+        info.episode = caps[1].parse().ok();
+        info.episode_end = caps[2].parse().ok();
+        episode_start = caps.get(0).map(|m| m.start());
+    } else {
+        for re in [
+            &*CJK_EPISODE,
+            &*DASH_EPISODE,
+            &*BRACKET_EPISODE,
+            &*EP_EPISODE,
+        ] {
+            if let Some(caps) = re.captures_iter(body).find(|caps| !is_year(&caps[1])) {
+                info.episode = caps[1].parse().ok();
+                episode_start = caps.get(0).map(|m| m.start());
+                break;
+            }
+        }
+    }
+    if info.season.is_none() {
+        info.season = SEASON.captures(body).and_then(|caps| {
+            caps.get(1)
+                .or(caps.get(2))
+                .or(caps.get(3))
+                .and_then(|m| parse_number(m.as_str()))
+        });
+    }
+
+    // 作品名称：发布组之后、集数之前的文本，若为空则取第一个非集数的方括号内容
+    let name_end = episode_start.unwrap_or(body.len());
+    let name = body[..name_end]
+        .split('[')
+        .next()
+        .unwrap_or("")
+        .trim_matches(|c: char| c.is_whitespace() || c == '-');
+    info.title = if !name.is_empty() {
+        Some(name.to_owned())
+    } else {
+        body.split(['[', ']'])
+            .map(|s| s.trim())
+            .find(|s| !s.is_empty() && !s.chars().all(|c| c.is_ascii_digit()))
+            .map(|s| s.to_owned())
+    };
+    info
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_dash_episode() {
+        let info = parse_title("[ANi] 葬送的芙莉蓮 - 05 [1080P][Baha][WEB-DL][AAC AVC][CHT][MP4]");
+        assert_eq!(info.group.as_deref(), Some("ANi"));
+        assert_eq!(info.title.as_deref(), Some("葬送的芙莉蓮"));
+        assert_eq!(info.episode, Some(5));
+        assert_eq!(info.resolution.as_deref(), Some("1080p"));
+    }
+
+    #[test]
+    fn test_parse_bracket_episode() {
+        let info = parse_title(
+            "【喵萌奶茶屋】★10月新番★[葬送的芙莉莲 / Sousou no Frieren][05][1080p][简日双语][招募翻译]",
+        );
+        assert_eq!(info.group.as_deref(), Some("喵萌奶茶屋"));
+        assert_eq!(
+            info.title.as_deref(),
+            Some("葬送的芙莉莲 / Sousou no Frieren")
+        );
+        assert_eq!(info.episode, Some(5));
+        assert_eq!(info.resolution.as_deref(), Some("1080p"));
+    }
+
+    #[test]
+    fn test_parse_season_and_range() {
+        let info = parse_title("[Nekomoe kissaten] Kusuriya no Hitorigoto S2 [01-12][1920x1080]");
+        assert_eq!(info.season, Some(2));
+        assert_eq!(info.episode, Some(1));
+        assert_eq!(info.episode_end, Some(12));
+        assert_eq!(info.resolution.as_deref(), Some("1080p"));
+
+        let info = parse_title("Frieren S01E28 1080p WEB H264");
+        assert_eq!(info.group, None);
+        assert_eq!(info.season, Some(1));
+        assert_eq!(info.episode, Some(28));
+
+        let info = parse_title("[LoliHouse] 药屋少女的呢喃 第二季 第13话 [WebRip 2160p]");
+        assert_eq!(info.season, Some(2));
+        assert_eq!(info.episode, Some(13));
+        assert_eq!(info.resolution.as_deref(), Some("2160p"));
+    }
+
+    #[test]
+    fn test_parse_ignores_year() {
+        let info = parse_title("[Group] Some Movie [2023][1080p]");
+        assert_eq!(info.episode, None);
+        assert_eq!(info.title.as_deref(), Some("Some Movie"));
+    }
+}
@@ -12,12 +12,16 @@ use tracing::{error, info};
 use crate::{
     rss::{rss_task, Rss},
     state::{Config, DataBase, SerdeLockLayer, State},
+    store::Store,
 };
 
 #[derive(Debug, Clone)]
 pub enum Event {
     AddRss(Rss),
+    /// 保存整个数据库，只有发生变化的记录会被写入
     SaveDatabase,
+    /// 只保存一个订阅及其条目
+    SaveRss(usize),
 }
 
 /// 处理事件的异步任务函数。
@@ -26,11 +30,13 @@ pub enum Event {
 /// # 参数
 /// * `config` - 配置信息的读写锁引用。
 /// * `db` - 数据库的读写锁引用。
+/// * `store` - 持久化数据的存储。
 /// * `sender` - 用于发送事件的通道发送端。
 /// * `receiver` - 用于接收事件的通道接收端。
 pub async fn event_handle_task(
     config: Arc<RwLock<Config>>,
     db: Arc<RwLock<DataBase>>,
+    store: Store,
     sender: Sender<Event>,
     state: Arc<RwLock<State>>,
     mut receiver: Receiver<Event>,
@@ -39,6 +45,19 @@ pub async fn event_handle_task(
     // 创建一个任务池，用于存储和管理异步任务。
     let mut rss_task_pool: HashMap<usize, JoinHandle<()>> = HashMap::new();
     //let mut jobset = tokio::task::JoinSet::new();
+    // 为已有的条目建立全文索引
+    {
+        let search_index = state.read().await.search_index.clone();
+        let mut search_index = search_index.write().await;
+        for rss in db.read().await.rss_list.values() {
+            let rss = rss.read().await;
+            for item in rss.items.iter() {
+                let item = item.read().await;
+                search_index.add_item((rss.id, item.id), &item);
+            }
+        }
+        info!("Indexed {} items", search_index.len());
+    }
     // 遍历数据库中的RSS列表，并为每个RSS源创建一个异步任务。
     for rss in db.write().await.rss_list.iter() {
         let handle = tokio::spawn(rss_task(rss.1.weak(), state.clone(), config.clone()));
@@ -59,19 +78,24 @@ pub async fn event_handle_task(
                 rss_task_pool.insert(id, handle);
                 // 将新RSS源添加到数据库。
                 db.write().await.rss_list.insert(id, lock);
-                // 发送保存数据库的事件。
-                sender.send(SaveDatabase).await.unwrap();
+                // 发送保存新RSS源的事件。
+                sender.send(SaveRss(id)).await.unwrap();
             }
             // 保存数据库。
             SaveDatabase => {
-                // 读取数据库并保存到配置文件指定的路径。
-                db.read()
-                    .await
-                    .save(config.read().await.db_path.as_str())
+                store
+                    .save(&db)
                     .await
                     .inspect_err(|e| error!("save database error: {}", e))
                     .ok();
             }
+            SaveRss(id) => {
+                store
+                    .save_rss(&db, id)
+                    .await
+                    .inspect_err(|e| error!("save rss {} error: {}", id, e))
+                    .ok();
+            }
         }
     }
 }
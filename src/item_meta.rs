@@ -0,0 +1,296 @@
+use std::time::SystemTime;
+
+use quick_xml::events::Event;
+use quick_xml::name::ResolveResult;
+use quick_xml::reader::NsReader;
+use time::{
+    format_description::well_known::{Rfc2822, Rfc3339},
+    macros::{format_description, offset},
+    OffsetDateTime, PrimitiveDateTime,
+};
+
+/// Mikan 在每个条目中附加的 `<torrent>` 元素所在的命名空间
+const MIKAN_NAMESPACE: &[u8] = b"https://mikanani.me/0.1/";
+
+/// 从订阅条目中直接获取的信息，不需要获取种子元数据
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ItemMeta {
+    pub guid: Option<String>,
+    pub pub_date: Option<SystemTime>,
+    pub categories: Vec<String>,
+    pub author: Option<String>,
+    /// 种子内容的总大小，单位为字节
+    pub size: Option<u64>,
+    pub info_hash: Option<String>,
+    pub seeders: Option<u32>,
+}
+
+/// Mikan `<torrent>` 元素中的字段，rss 库会丢弃没有前缀的扩展元素，因此需要单独解析
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MikanTorrent {
+    pub content_length: Option<u64>,
+    pub pub_date: Option<SystemTime>,
+}
+
+/// 解析 RFC 2822 或 RFC 3339 格式的时间
+pub fn parse_date(s: &str) -> Option<SystemTime> {
+    let s = s.trim();
+    OffsetDateTime::parse(s, &Rfc2822)
+        .or_else(|_| OffsetDateTime::parse(s, &Rfc3339))
+        .ok()
+        .map(SystemTime::from)
+}
+
+/// 解析 Mikan 的时间，格式为 `2024-01-06T18:31:22.58`，没有时区，使用北京时间
+fn parse_mikan_date(s: &str) -> Option<SystemTime> {
+    let s = s.trim().split('.').next()?;
+    PrimitiveDateTime::parse(
+        s,
+        format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]"),
+    )
+    .ok()
+    .map(|t| t.assume_offset(offset!(+8)).into())
+}
+
+/// 解析 `1.2 GiB` 这类带单位的大小
+pub fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let value: f64 = s[..split].parse().ok()?;
+    let unit = match s[split..].trim().to_ascii_lowercase().as_str() {
+        "" | "b" | "bytes" => 1u64,
+        "kib" | "kb" => 1 << 10,
+        "mib" | "mb" => 1 << 20,
+        "gib" | "gb" => 1 << 30,
+        "tib" | "tb" => 1 << 40,
+        _ => return None,
+    };
+    Some((value * unit as f64) as u64)
+}
+
+/// 规范化 infohash，40 位十六进制转为小写，32 位 base32 转为十六进制，其他格式返回 `None`
+pub fn normalize_info_hash(hash: &str) -> Option<String> {
+    if hash.len() == 40 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Some(hash.to_ascii_lowercase());
+    }
+    if hash.len() != 32 {
+        return None;
+    }
+    let mut hex = String::with_capacity(40);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in hash.bytes() {
+        let value = match c.to_ascii_uppercase() {
+            c @ b'A'..=b'Z' => c - b'A',
+            c @ b'2'..=b'7' => c - b'2' + 26,
+            _ => return None,
+        };
+        buffer = ((buffer << 5) | value as u32) & 0xffff;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            hex.push_str(&format!("{:02x}", (buffer >> bits) & 0xff));
+        }
+    }
+    Some(hex)
+}
+
+/// 从磁力链接中取出 infohash
+pub fn magnet_info_hash(link: &str) -> Option<String> {
+    let (_, rest) = link.split_once("xt=urn:btih:")?;
+    normalize_info_hash(rest.split('&').next()?)
+}
+
+impl ItemMeta {
+    /// 读取标准字段、`enclosure` 的长度以及 nyaa 的 `nyaa:` 扩展
+    pub fn from_item(item: &::rss::Item) -> Self {
+        let nyaa = |name: &str| {
+            item.extensions()
+                .get("nyaa")
+                .and_then(|ext| ext.get(name))
+                .and_then(|values| values.first())
+                .and_then(|ext| ext.value())
+                .map(|v| v.trim().to_owned())
+        };
+        let mut categories: Vec<String> = item
+            .categories()
+            .iter()
+            .map(|c| c.name().to_owned())
+            .collect();
+        categories.extend(nyaa("category"));
+        Self {
+            guid: item.guid().map(|g| g.value().to_owned()),
+            pub_date: item.pub_date().and_then(parse_date),
+            categories,
+            author: item
+                .author()
+                .or(item
+                    .dublin_core_ext()
+                    .and_then(|dc| dc.creators().first().map(|s| s.as_str())))
+                .map(|s| s.to_owned()),
+            size: item
+                .enclosure()
+                .and_then(|e| e.length().parse().ok())
+                .filter(|length| *length > 0)
+                .or(nyaa("size").as_deref().and_then(parse_size)),
+            info_hash: nyaa("infoHash")
+                .as_deref()
+                .and_then(normalize_info_hash)
+                .or(item.enclosure().and_then(|e| magnet_info_hash(e.url())))
+                .or(item.link().and_then(magnet_info_hash)),
+            seeders: nyaa("seeders").and_then(|s| s.parse().ok()),
+        }
+    }
+
+    /// 合并 Mikan `<torrent>` 元素中的字段，标准字段优先
+    pub fn merge_mikan(&mut self, torrent: &MikanTorrent) {
+        self.size = self.size.or(torrent.content_length);
+        self.pub_date = self.pub_date.or(torrent.pub_date);
+    }
+}
+
+/// 按条目顺序解析 Mikan 的 `<torrent>` 元素，返回值与频道中的条目一一对应
+pub fn parse_mikan_torrents(content: &[u8]) -> Vec<Option<MikanTorrent>> {
+    let mut reader = NsReader::from_reader(content);
+    reader.config_mut().trim_text(true);
+    let mut buf = Vec::new();
+    let mut torrents = Vec::new();
+    // 当前条目的 torrent 元素，以及当前所在的 torrent 子元素
+    let mut current: Option<MikanTorrent> = None;
+    let mut in_item = false;
+    let mut field: Option<Vec<u8>> = None;
+    while let Ok(event) = reader.read_resolved_event_into(&mut buf) {
+        match event {
+            (ResolveResult::Bound(ns), Event::Start(e)) if ns.as_ref() == MIKAN_NAMESPACE => {
+                if in_item && e.local_name().as_ref() == b"torrent" {
+                    current.get_or_insert_with(MikanTorrent::default);
+                } else if current.is_some() {
+                    field = Some(e.local_name().as_ref().to_vec());
+                }
+            }
+            (_, Event::Start(e)) if e.local_name().as_ref() == b"item" => {
+                in_item = true;
+                current = None;
+            }
+            (_, Event::Text(text)) => {
+                if let (Some(torrent), Some(name), Ok(text)) =
+                    (current.as_mut(), field.as_deref(), text.unescape())
+                {
+                    match name {
+                        b"contentLength" => torrent.content_length = text.trim().parse().ok(),
+                        b"pubDate" => torrent.pub_date = parse_mikan_date(&text),
+                        _ => {}
+                    }
+                }
+            }
+            (_, Event::End(e)) if e.local_name().as_ref() == b"item" => {
+                if in_item {
+                    torrents.push(current.take());
+                }
+                in_item = false;
+            }
+            (_, Event::End(_)) => field = None,
+            (_, Event::Eof) => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    torrents
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_parse_mikan() {
+        let content = br#"<?xml version="1.0" encoding="utf-8"?>
+<rss version="2.0"><channel><title>Mikan Project</title>
+<item>
+  <guid isPermaLink="false">[ANi] Frieren - 05 [1080P]</guid>
+  <link>https://mikanani.me/Home/Episode/abc</link>
+  <title>[ANi] Frieren - 05 [1080P]</title>
+  <torrent xmlns="https://mikanani.me/0.1/">
+    <link>https://mikanani.me/Home/Episode/abc</link>
+    <contentLength>365112128</contentLength>
+    <pubDate>2024-01-06T18:31:22.58</pubDate>
+  </torrent>
+  <enclosure type="application/x-bittorrent" length="365112128" url="https://mikanani.me/Download/20240106/abc.torrent" />
+</item>
+<item><title>no torrent</title></item>
+</channel></rss>"#;
+        let torrents = parse_mikan_torrents(content);
+        assert_eq!(torrents.len(), 2);
+        let torrent = torrents[0].clone().unwrap();
+        assert_eq!(torrent.content_length, Some(365112128));
+        // 北京时间 18:31:22 即 UTC 10:31:22
+        assert_eq!(
+            torrent.pub_date,
+            Some(UNIX_EPOCH + Duration::from_secs(1704537082))
+        );
+        assert_eq!(torrents[1], None);
+
+        let channel = ::rss::Channel::read_from(&content[..]).unwrap();
+        let meta = ItemMeta::from_item(&channel.items()[0]);
+        assert_eq!(meta.guid.as_deref(), Some("[ANi] Frieren - 05 [1080P]"));
+        assert_eq!(meta.size, Some(365112128));
+        assert_eq!(meta.pub_date, None);
+    }
+
+    #[test]
+    fn test_parse_nyaa() {
+        let content = br#"<?xml version="1.0" encoding="UTF-8"?>
+<rss xmlns:atom="http://www.w3.org/2005/Atom" xmlns:nyaa="https://nyaa.si/xmlns/nyaa" version="2.0">
+<channel><title>Nyaa</title><link>https://nyaa.si/</link><description>RSS</description>
+<item>
+  <title>[SubsPlease] Frieren - 05 (1080p)</title>
+  <link>https://nyaa.si/download/1.torrent</link>
+  <guid isPermaLink="true">https://nyaa.si/view/1</guid>
+  <pubDate>Sat, 06 Jan 2024 10:31:22 -0000</pubDate>
+  <nyaa:seeders>1234</nyaa:seeders>
+  <nyaa:infoHash>0123456789ABCDEF0123456789ABCDEF01234567</nyaa:infoHash>
+  <nyaa:category>Anime - English-translated</nyaa:category>
+  <nyaa:size>1.4 GiB</nyaa:size>
+</item>
+</channel></rss>"#;
+        let channel = ::rss::Channel::read_from(&content[..]).unwrap();
+        let meta = ItemMeta::from_item(&channel.items()[0]);
+        assert_eq!(meta.guid.as_deref(), Some("https://nyaa.si/view/1"));
+        assert_eq!(
+            meta.pub_date,
+            Some(UNIX_EPOCH + Duration::from_secs(1704537082))
+        );
+        assert_eq!(meta.seeders, Some(1234));
+        assert_eq!(
+            meta.info_hash.as_deref(),
+            Some("0123456789abcdef0123456789abcdef01234567")
+        );
+        assert_eq!(meta.categories, vec!["Anime - English-translated"]);
+        assert_eq!(meta.size, Some(1503238553));
+    }
+
+    #[test]
+    fn test_normalize_info_hash() {
+        let hex = Some("0123456789abcdef0123456789abcdef01234567".to_owned());
+        assert_eq!(
+            normalize_info_hash("0123456789ABCDEF0123456789ABCDEF01234567"),
+            hex
+        );
+        assert_eq!(normalize_info_hash("AERUKZ4JVPG66AJDIVTYTK6N54ASGRLH"), hex);
+        assert_eq!(
+            magnet_info_hash("magnet:?xt=urn:btih:aeruKZ4JVPG66AJDIVTYTK6N54ASGRLH&dn=a"),
+            hex
+        );
+        assert_eq!(normalize_info_hash("../../../../etc/passwd"), None);
+        assert_eq!(
+            normalize_info_hash("0123456789abcdef0123456789abcdef0123456/"),
+            None
+        );
+        assert_eq!(
+            normalize_info_hash("AERUKZ4JVPG66AJDIVTYTK6N54ASGRL1"),
+            None
+        );
+    }
+}
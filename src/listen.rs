@@ -0,0 +1,169 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use salvo::prelude::*;
+use tokio::{sync::watch, task::JoinSet};
+use tracing::{error, info};
+
+use crate::{state::Config, tls};
+
+/// Unix 域套接字地址的前缀，例如 `unix:/run/nekodl/nekodl.sock`
+const UNIX_PREFIX: &str = "unix:";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindAddress {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl std::fmt::Display for BindAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "{}", addr),
+            Self::Unix(path) => write!(f, "{}{}", UNIX_PREFIX, path.display()),
+        }
+    }
+}
+
+/// 解析监听地址，多个地址用逗号分隔
+pub fn parse_bind_addresses(s: &str) -> Result<Vec<BindAddress>> {
+    let addresses = s
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| match s.strip_prefix(UNIX_PREFIX) {
+            Some(path) if !path.is_empty() => Ok(BindAddress::Unix(path.into())),
+            Some(_) => Err(anyhow!("Missing unix socket path")),
+            None => s
+                .parse()
+                .map(BindAddress::Tcp)
+                .map_err(|_| anyhow!("Invalid address {}", s)),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if addresses.is_empty() {
+        return Err(anyhow!("No address to listen on"));
+    }
+    Ok(addresses)
+}
+
+/// 解析八进制的文件权限，例如 `660`
+pub fn parse_mode(s: &str) -> Result<u32> {
+    u32::from_str_radix(s.trim_start_matches("0o"), 8)
+        .ok()
+        .filter(|mode| *mode <= 0o777)
+        .ok_or_else(|| anyhow!("Invalid permission mode {}", s))
+}
+
+/// 删除上次运行留下的套接字文件，其他类型的文件不删除
+#[cfg(unix)]
+fn remove_stale_socket(path: &std::path::Path) -> Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if metadata.file_type().is_socket() {
+            std::fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_mode(path: &std::path::Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+/// 在所有地址上启动服务，任一监听器绑定失败时返回错误。
+/// 每个监听器使用 `make_service` 创建独立的服务，配置了 TLS 时 TCP 监听器使用 HTTPS。
+pub async fn serve(config: &Config, make_service: impl Fn() -> Service) -> Result<()> {
+    let addresses = parse_bind_addresses(&config.bind_address)?;
+    let mode = config
+        .unix_socket_mode
+        .as_deref()
+        .map(parse_mode)
+        .transpose()?;
+    let reload = match &config.tls {
+        Some(options) => {
+            tls::ensure_certificate(options, &addresses)?;
+            let (sender, receiver) = watch::channel(());
+            tokio::spawn(tls::tls_reload_task(options.clone(), sender));
+            Some((options, receiver))
+        }
+        None => None,
+    };
+    let mut servers = JoinSet::new();
+    for address in addresses {
+        match &address {
+            BindAddress::Tcp(addr) => {
+                let listener = TcpListener::new(*addr);
+                if let Some((options, receiver)) = &reload {
+                    let stream = tls::config_stream((*options).clone(), receiver.clone())?;
+                    let acceptor = listener
+                        .rustls(stream)
+                        .try_bind()
+                        .await
+                        .with_context(|| format!("Listen on {}", address))?;
+                    servers.spawn(Server::new(acceptor).serve(make_service()));
+                } else {
+                    let acceptor = listener
+                        .try_bind()
+                        .await
+                        .with_context(|| format!("Listen on {}", address))?;
+                    servers.spawn(Server::new(acceptor).serve(make_service()));
+                }
+            }
+            #[cfg(unix)]
+            BindAddress::Unix(path) => {
+                remove_stale_socket(path)?;
+                let acceptor = salvo::conn::UnixListener::new(path.clone())
+                    .try_bind()
+                    .await
+                    .with_context(|| format!("Listen on {}", address))?;
+                if let Some(mode) = mode {
+                    set_mode(path, mode)?;
+                }
+                servers.spawn(Server::new(acceptor).serve(make_service()));
+            }
+            #[cfg(not(unix))]
+            BindAddress::Unix(_) => {
+                let _ = mode;
+                return Err(anyhow!("Unix sockets are not supported on this platform"));
+            }
+        }
+        let scheme = match (&address, &reload) {
+            (BindAddress::Tcp(_), Some(_)) => "https",
+            _ => "http",
+        };
+        info!("Listening on {} ({})", address, scheme);
+    }
+    while let Some(res) = servers.join_next().await {
+        if let Err(e) = res {
+            error!("Server stopped: {}", e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_bind_addresses() {
+        assert_eq!(
+            parse_bind_addresses("[::]:8001, 127.0.0.1:8002,unix:/run/nekodl.sock").unwrap(),
+            vec![
+                BindAddress::Tcp("[::]:8001".parse().unwrap()),
+                BindAddress::Tcp("127.0.0.1:8002".parse().unwrap()),
+                BindAddress::Unix("/run/nekodl.sock".into()),
+            ]
+        );
+        assert!(parse_bind_addresses("localhost").is_err());
+        assert!(parse_bind_addresses("unix:").is_err());
+        assert!(parse_bind_addresses(" , ").is_err());
+        assert_eq!(parse_mode("0660").unwrap(), 0o660);
+        assert!(parse_mode("999").is_err());
+    }
+}
@@ -1,35 +1,153 @@
 #![allow(dead_code)]
 use clap::Parser;
+use config::Overrides;
 use event::event_handle_task;
+use metadata::{metadata_task, MetadataQueue};
 use salvo::cors::{AllowCredentials, AllowHeaders, AllowMethods, Cors};
 use salvo::prelude::*;
-use state::{data_save_task, Config, DataBase, State};
-use std::collections::HashMap;
-use std::fs::read;
-use std::io;
+use search::SearchIndex;
+use series::series_task;
+use session::Sessions;
+use state::{data_save_task, Config, State};
+use std::path::PathBuf;
 use std::sync::Arc;
+use store::Store;
 use tokio::sync::mpsc;
-use tokio::{fs::write, sync::RwLock};
-use tracing::info;
+use tokio::sync::RwLock;
+use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
-use utils::{rand_str, sha256};
+use utils::rand_str;
 
 mod api;
+mod api_key;
+mod backup;
+mod calendar;
+mod config;
 mod downloader;
+mod episode;
 mod event;
+mod item_meta;
+mod listen;
+mod metadata;
+mod opml;
+mod password;
+mod retention;
 mod rss;
+mod search;
+mod series;
+mod session;
 mod state;
 mod static_serv;
+mod store;
 mod task;
+mod tls;
 mod torrent;
+mod torrent_cache;
+mod typed_store;
+mod user;
 mod utils;
 
+/// 配置按默认值、配置文件、`NEKODL_*` 环境变量、命令行参数的顺序合并，后者优先
 #[derive(clap::Parser)]
 struct App {
-    #[arg(short = 'b')]
+    /// 监听地址
+    #[arg(short = 'b', long)]
     bind: Option<String>,
-    #[arg(short = 'c')]
-    config: Option<String>,
+    /// 配置文件路径，扩展名为 .toml 时使用 TOML 格式
+    #[arg(short = 'c', long, default_value = "./config.json")]
+    config: PathBuf,
+    #[arg(long)]
+    db_path: Option<String>,
+    #[arg(long)]
+    session_path: Option<String>,
+    #[arg(long)]
+    output_path: Option<String>,
+    /// 覆盖任意配置项，例如 `--set metadata_options.concurrency=8`
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    set: Vec<(String, String)>,
+    /// 输出合并后的配置并退出，密码和密钥会被隐藏
+    #[arg(long)]
+    print_config: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+fn parse_key_value(arg: &str) -> Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .ok_or_else(|| format!("expected KEY=VALUE, got {}", arg))
+}
+
+impl App {
+    /// 环境变量和命令行参数对配置的覆盖，命令行参数在后
+    fn overrides(&self) -> Overrides {
+        let mut overrides = Overrides::from_env();
+        let flags = [
+            ("bind_address", &self.bind),
+            ("db_path", &self.db_path),
+            ("session_path", &self.session_path),
+            ("output_path", &self.output_path),
+        ];
+        for (key, value) in flags {
+            if let Some(value) = value {
+                overrides.set(key, value);
+            }
+        }
+        for (key, value) in self.set.iter() {
+            overrides.set(key, value);
+        }
+        overrides
+    }
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// 数据库维护
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+    /// 重置登录密码，修改配置文件后运行中的服务会自动重新加载
+    Passwd {
+        /// 同时修改内置账户的用户名
+        #[arg(long)]
+        username: Option<String>,
+        /// 重置其他用户的密码，默认为内置账户
+        #[arg(long, conflicts_with = "username")]
+        user: Option<String>,
+        /// 从标准输入读取新密码，不进行交互式输入
+        #[arg(long)]
+        stdin: bool,
+    },
+}
+
+#[derive(clap::Subcommand)]
+enum DbCommand {
+    /// 将数据库升级到当前版本
+    Migrate {
+        /// 只显示会发生的变化，不修改数据库
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// 读取新密码，交互式输入时需要输入两次
+fn read_new_password(stdin: bool) -> anyhow::Result<String> {
+    let password = if stdin {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        line.trim_end_matches(['\r', '\n']).to_owned()
+    } else {
+        let password = rpassword::prompt_password("新密码: ")?;
+        if password != rpassword::prompt_password("再次输入新密码: ")? {
+            anyhow::bail!("两次输入的密码不一致");
+        }
+        password
+    };
+    if password.is_empty() {
+        anyhow::bail!("密码不能为空");
+    }
+    Ok(password)
 }
 
 #[tokio::main]
@@ -42,52 +160,105 @@ async fn main() -> anyhow::Result<()> {
     // 解析命令行参数
     let app = App::parse();
 
-    // 加载或创建配置文件
-    let mut config = if let Some(conf_path) = &app.config {
-        info!("从 {} 读取配置", conf_path);
-        Config::from_path(conf_path.into())?
+    // 读取配置文件，不存在时使用默认配置
+    let config_path = app.config.clone();
+    let file_config = if config_path.exists() {
+        info!("从 {} 读取配置", config_path.display());
+        Config::load(&config_path)?
     } else {
         info!("创建默认配置");
-        let rand_pw = rand_str(8); // 生成随机密码
-        let sha_256_pw = sha256(&rand_pw); // 对密码进行 SHA-256 哈希
-        println!("默认账户: 用户名: admin, 密码: {}", rand_pw);
-        let config = Config::default().update_password(sha_256_pw);
-        write("./config.json", serde_json::to_string(&config)?).await?; // 将默认配置写入文件
-        config
+        Config::default()
     };
+    // 合并环境变量和命令行参数
+    let overrides = app.overrides();
+    let mut config = overrides.apply(&file_config)?;
 
-    // 如果命令行参数中指定了绑定地址，则更新配置
-    if let Some(bind_addr) = &app.bind {
-        config = config.update_bind_addr(bind_addr.clone())
+    if app.print_config {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&config::masked(&config))?
+        );
+        return Ok(());
     }
 
-    // 读取数据库数据
-    let db_data = read(config.db_path.as_str());
-
-    // 反序列化数据库数据或创建新的数据库实例
-    let db: DataBase = match db_data {
-        Ok(data) => bincode::deserialize(&data)?,
-        Err(e) => match e.kind() {
-            io::ErrorKind::NotFound => {
-                let db = DataBase {
-                    rss_id_index: 0,
-                    rss_list: HashMap::new(),
-                    //download_task_list: Vec::new(),
-                };
-                let data = bincode::serialize(&db)?;
-                write(config.db_path.as_str(), data).await?; // 将新的数据库实例写入文件
-                db
-            }
-            _ => {
-                panic!("读取数据库错误: {}", e);
-            }
-        },
+    // 执行子命令，不启动服务
+    if let Some(Command::Db {
+        command: DbCommand::Migrate { dry_run },
+    }) = &app.command
+    {
+        for line in store::migrate(&config.db_path, *dry_run).await? {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
+    if let Some(Command::Passwd {
+        username,
+        user,
+        stdin,
+    }) = &app.command
+    {
+        let mut saved = file_config.clone();
+        if let Some(username) = username {
+            saved.username = username.clone();
+        }
+        let name = user.clone().unwrap_or_else(|| saved.username.clone());
+        if saved.account(&name).is_none() {
+            anyhow::bail!("用户 {} 不存在", name);
+        }
+        saved.set_password(&name, password::hash(&read_new_password(*stdin)?)?);
+        saved.save(&config_path)?;
+        println!("已更新 {} 的密码", name);
+        return Ok(());
+    }
+
+    // 没有设置密码时生成随机密码
+    if config.password.is_empty() {
+        let rand_pw = rand_str(8); // 生成随机密码
+        let hashed_pw = password::hash(&rand_pw)?; // 使用 Argon2id 保存密码
+        println!("默认账户: 用户名: {}, 密码: {}", config.username, rand_pw);
+        config = config.update_password(hashed_pw);
+    }
+
+    // 生成已完成下载订阅源的访问密钥
+    if config.feed_key.is_none() {
+        config.feed_key = Some(rand_str(32));
+    }
+
+    // 将生成的内容写入配置文件，覆盖的值不写入
+    let saved = overrides.strip(&config, &file_config)?;
+    if !config_path.exists() || serde_json::to_value(&saved)? != serde_json::to_value(&file_config)?
+    {
+        saved.save(&config_path)?;
+    }
+
+    // 打开数据库，旧版本的 db.bin 会被导入到新数据库中
+    let (store, db) = Store::open_or_import(&config.db_path).await?;
+
+    // 读取保存的登录会话
+    let sessions = if config.session_options.persist {
+        store.load_sessions().await.unwrap_or_else(|e| {
+            error!("Failed to load sessions: {:#}", e);
+            Sessions::default()
+        })
+    } else {
+        Sessions::default()
     };
+    // 读取 API 密钥，读取失败时不启动，避免保存时覆盖已有的密钥
+    let api_keys = store.load_api_keys().await?;
+    // 读取下载任务的所有者，读取失败时不启动，原因同上
+    let download_tasks = store.load_download_tasks().await?;
+
+    // 创建元数据获取队列
+    let (metadata_queue, metadata_receiver) = MetadataQueue::new();
 
     // 创建共享状态
     let state = Arc::new(RwLock::new(State {
-        token: None,
+        sessions,
+        api_keys,
         rqbit_session: None,
+        metadata_queue: metadata_queue.clone(),
+        search_index: Arc::new(RwLock::new(SearchIndex::new())),
+        download_tasks,
     }));
     let config = Arc::new(RwLock::new(config));
     let db = Arc::new(RwLock::new(db));
@@ -95,10 +266,32 @@ async fn main() -> anyhow::Result<()> {
     // 启动数据保存任务
     tokio::spawn(data_save_task(
         db.clone(),
+        store.clone(),
+        config.clone(),
+        state.clone(),
+        config_path.clone(),
+        overrides.clone(),
+    ));
+
+    // 启动配置文件监视任务
+    tokio::spawn(config::config_watch_task(
+        config.clone(),
+        state.clone(),
+        config_path,
+        overrides,
+    ));
+
+    // 启动元数据获取任务
+    tokio::spawn(metadata_task(
+        metadata_queue,
+        metadata_receiver,
+        state.clone(),
         config.clone(),
-        app.config.unwrap_or("./config.json".to_owned()),
     ));
 
+    // 启动剧集自动补全任务
+    tokio::spawn(series_task(db.clone(), state.clone(), config.clone()));
+
     // 创建消息通道
     let event_task_channel = mpsc::channel(1000);
 
@@ -106,6 +299,7 @@ async fn main() -> anyhow::Result<()> {
     tokio::spawn(event_handle_task(
         config.clone(),
         db.clone(),
+        store.clone(),
         event_task_channel.0.clone(),
         state.clone(),
         event_task_channel.1,
@@ -118,30 +312,33 @@ async fn main() -> anyhow::Result<()> {
         config.clone(),
     ));*/
 
-    // 创建 TCP 监听器
-    let sock = TcpListener::new("[::]:8001").bind().await;
-
-    // 创建路由器并添加中间件和路由
-    let router = Router::new()
-        .hoop(affix_state::inject(event_task_channel.0))
-        //.hoop(affix_state::inject(download_task_channel.0))
-        .hoop(affix_state::inject(config))
-        .hoop(affix_state::inject(state))
-        .hoop(affix_state::inject(db))
-        .push(Router::with_path("/api").append(&mut api::routes()));
-
-    // 创建服务并添加 CORS 中间件
-    let service = Service::new(router).hoop(
-        Cors::new()
-            .allow_origin("*")
-            .allow_methods(AllowMethods::any())
-            .allow_credentials(AllowCredentials::judge(|_, _, _| true))
-            .allow_headers(AllowHeaders::any())
-            .into_handler(),
-    );
-
-    // 启动服务器
-    Server::new(sock).serve(service).await;
-
-    Ok(())
+    let listen_config = config.read().await.clone();
+
+    // 每个监听器使用一个服务
+    let make_service = move || {
+        // 创建路由器并添加中间件和路由
+        let router = Router::new()
+            .hoop(affix_state::inject(event_task_channel.0.clone()))
+            //.hoop(affix_state::inject(download_task_channel.0))
+            .hoop(affix_state::inject(config.clone()))
+            .hoop(affix_state::inject(state.clone()))
+            .hoop(affix_state::inject(db.clone()))
+            .hoop(affix_state::inject(store.clone()))
+            .push(Router::with_path("/api").append(&mut api::routes()))
+            // 其余路径提供网页界面
+            .push(Router::with_path("<**path>").get(static_serv::webui));
+
+        // 创建服务并添加 CORS 中间件
+        Service::new(router).hoop(
+            Cors::new()
+                .allow_origin("*")
+                .allow_methods(AllowMethods::any())
+                .allow_credentials(AllowCredentials::judge(|_, _, _| true))
+                .allow_headers(AllowHeaders::any())
+                .into_handler(),
+        )
+    };
+
+    // 在配置的地址上启动服务器
+    listen::serve(&listen_config, make_service).await
 }
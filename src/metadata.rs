@@ -0,0 +1,456 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashSet},
+    future::Future,
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        OwnedSemaphorePermit, RwLock, Semaphore,
+    },
+    time::{sleep, timeout},
+};
+use tracing::{info, warn};
+use ts_rs::TS;
+
+use crate::{
+    rss::RssItem,
+    state::{Config, State},
+    torrent::fetch_torrent_for_item,
+    torrent_cache::TorrentCache,
+};
+
+/// 条目种子元数据的获取状态
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub enum MetadataState {
+    /// 尚未加入获取队列
+    #[default]
+    Idle,
+    Queued,
+    Fetching {
+        attempt: u32,
+    },
+    /// 获取失败，等待重试
+    Retrying {
+        attempts: u32,
+        error: String,
+    },
+    Fetched,
+    /// 重试次数用尽
+    Failed {
+        attempts: u32,
+        error: String,
+        /// 最后一次失败的时间
+        #[serde(default)]
+        time: Option<SystemTime>,
+    },
+}
+
+impl MetadataState {
+    /// 是否需要（重新）加入获取队列，重启前处于队列中的条目也需要重新加入。
+    /// 获取失败的条目在按失败次数计算的等待时间过后重新获取
+    pub fn needs_fetch(&self, options: &MetadataOptions) -> bool {
+        match self {
+            Self::Fetched => false,
+            Self::Failed { attempts, time, .. } => time.is_none_or(|time| {
+                time.elapsed()
+                    .is_ok_and(|elapsed| elapsed >= options.backoff(*attempts))
+            }),
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct MetadataOptions {
+    /// 同时获取元数据的最大数量
+    pub concurrency: usize,
+    /// 单次获取的超时时间，单位为秒
+    pub timeout: u64,
+    /// 失败后的最大重试次数
+    pub max_retries: u32,
+    /// 首次重试前的等待时间，单位为秒，之后每次翻倍
+    pub retry_backoff: u64,
+}
+
+impl Default for MetadataOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            timeout: 120,
+            max_retries: 3,
+            retry_backoff: 30,
+        }
+    }
+}
+
+impl MetadataOptions {
+    /// 第 `attempt` 次获取失败后，重试前的等待时间
+    fn backoff(&self, attempt: u32) -> Duration {
+        Duration::from_secs(self.retry_backoff << attempt.saturating_sub(1).min(16))
+    }
+}
+
+pub struct MetadataJob {
+    /// (rss id, item id)
+    pub key: (usize, usize),
+    pub item: Weak<RwLock<RssItem>>,
+    /// 订阅所属的分类，用于选择 tracker
+    pub category: Option<String>,
+    /// 优先级，数值越大越先获取
+    pub priority: i32,
+}
+
+/// 等待获取的任务，按优先级排序，同优先级先进先出
+struct PendingJob {
+    seq: u64,
+    job: MetadataJob,
+}
+
+impl PartialEq for PendingJob {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PendingJob {}
+
+impl PartialOrd for PendingJob {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PendingJob {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.job
+            .priority
+            .cmp(&other.job.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+/// 元数据获取队列，同一条目在获取完成前只会被加入一次
+#[derive(Clone)]
+pub struct MetadataQueue {
+    sender: UnboundedSender<MetadataJob>,
+    in_flight: Arc<Mutex<HashSet<(usize, usize)>>>,
+}
+
+impl MetadataQueue {
+    pub fn new() -> (Self, UnboundedReceiver<MetadataJob>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (
+            Self {
+                sender,
+                in_flight: Arc::new(Mutex::new(HashSet::new())),
+            },
+            receiver,
+        )
+    }
+
+    /// 将条目加入队列，条目已在队列中时返回 `false`。
+    /// 调用方需要自行将条目状态设置为 [`MetadataState::Queued`]，以免在持有条目锁时死锁。
+    pub fn enqueue(&self, job: MetadataJob) -> bool {
+        {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            if !in_flight.insert(job.key) {
+                return false;
+            }
+        }
+        let key = job.key;
+        if self.sender.send(job).is_err() {
+            self.finish(key);
+            return false;
+        }
+        true
+    }
+
+    pub fn is_in_flight(&self, key: (usize, usize)) -> bool {
+        self.in_flight.lock().unwrap().contains(&key)
+    }
+
+    fn finish(&self, key: (usize, usize)) {
+        self.in_flight.lock().unwrap().remove(&key);
+    }
+}
+
+async fn set_state(item: &Weak<RwLock<RssItem>>, state: MetadataState) -> Result<()> {
+    item.upgrade()
+        .context("Item has been removed")?
+        .write()
+        .await
+        .metadata = state;
+    Ok(())
+}
+
+/// 获取一次条目的种子元数据
+async fn fetch_once(
+    job: &MetadataJob,
+    state: &Arc<RwLock<State>>,
+    config: &Arc<RwLock<Config>>,
+) -> Result<()> {
+    let session = state
+        .read()
+        .await
+        .rqbit_session
+        .clone()
+        .context("librqbit session not found")?;
+    let (trackers, cache) = {
+        let config = config.read().await;
+        (
+            config.trackers(job.category.as_deref()),
+            TorrentCache::new(&config.session_path),
+        )
+    };
+    fetch_torrent_for_item(&cache, session, trackers, job.item.clone())
+        .await
+        .map(|_| ())
+}
+
+/// 按照 `options` 的超时和重试设置反复调用 `fetch`，并更新条目的获取状态
+async fn fetch_with_retry<F, Fut>(
+    job: &MetadataJob,
+    mut permit: Option<OwnedSemaphorePermit>,
+    semaphore: &Arc<Semaphore>,
+    options: &MetadataOptions,
+    mut fetch: F,
+) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let result = {
+            // 只在实际获取时占用并发名额，等待重试期间不占用
+            let _permit = match permit.take() {
+                Some(permit) => permit,
+                None => semaphore.clone().acquire_owned().await?,
+            };
+            set_state(&job.item, MetadataState::Fetching { attempt }).await?;
+            match timeout(Duration::from_secs(options.timeout), fetch()).await {
+                Ok(res) => res,
+                Err(_) => Err(anyhow!("Timed out after {}s", options.timeout)),
+            }
+        };
+        match result {
+            Ok(()) => return set_state(&job.item, MetadataState::Fetched).await,
+            Err(e) if attempt > options.max_retries => {
+                set_state(
+                    &job.item,
+                    MetadataState::Failed {
+                        attempts: attempt,
+                        error: e.to_string(),
+                        time: Some(SystemTime::now()),
+                    },
+                )
+                .await?;
+                return Err(e);
+            }
+            Err(e) => {
+                warn!(
+                    "fetch metadata for {:?} failed (attempt {}): {}",
+                    job.key, attempt, e
+                );
+                set_state(
+                    &job.item,
+                    MetadataState::Retrying {
+                        attempts: attempt,
+                        error: e.to_string(),
+                    },
+                )
+                .await?;
+                sleep(options.backoff(attempt)).await;
+            }
+        }
+    }
+}
+
+/// 元数据获取任务，从队列中取出条目并以有限的并发数获取种子元数据
+pub async fn metadata_task(
+    queue: MetadataQueue,
+    mut receiver: UnboundedReceiver<MetadataJob>,
+    state: Arc<RwLock<State>>,
+    config: Arc<RwLock<Config>>,
+) {
+    let concurrency = config.read().await.metadata_options.concurrency.max(1);
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let mut pending = BinaryHeap::new();
+    let mut seq = 0;
+    loop {
+        tokio::select! {
+            // 优先接收新任务，保证取出的总是当前优先级最高的任务
+            biased;
+            job = receiver.recv() => match job {
+                Some(job) => {
+                    seq += 1;
+                    pending.push(PendingJob { seq, job });
+                }
+                None => break,
+            },
+            permit = semaphore.clone().acquire_owned(), if !pending.is_empty() => {
+                let Ok(permit) = permit else { break };
+                let Some(PendingJob { job, .. }) = pending.pop() else { continue };
+                let queue = queue.clone();
+                let semaphore = semaphore.clone();
+                let state = state.clone();
+                let config = config.clone();
+                tokio::spawn(async move {
+                    let options = config.read().await.metadata_options.clone();
+                    let fetch = || fetch_once(&job, &state, &config);
+                    match fetch_with_retry(&job, Some(permit), &semaphore, &options, fetch).await {
+                        Ok(()) => info!("fetched metadata for {:?}", job.key),
+                        Err(e) => warn!("give up fetching metadata for {:?}: {}", job.key, e),
+                    }
+                    queue.finish(job.key);
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicU32, Ordering as AtomicOrdering};
+
+    use super::*;
+
+    fn job(key: (usize, usize), priority: i32, item: Weak<RwLock<RssItem>>) -> MetadataJob {
+        MetadataJob {
+            key,
+            item,
+            category: None,
+            priority,
+        }
+    }
+
+    fn item() -> Arc<RwLock<RssItem>> {
+        let item = serde_json::from_value(serde_json::json!({
+            "title": "[ANi] Frieren - 01",
+            "link": "https://example.com/1.torrent",
+            "description": "",
+            "status": "Unread",
+            "torrent": null,
+            "id": 1,
+        }))
+        .unwrap();
+        Arc::new(RwLock::new(item))
+    }
+
+    fn options(max_retries: u32) -> MetadataOptions {
+        MetadataOptions {
+            concurrency: 1,
+            timeout: 0,
+            max_retries,
+            retry_backoff: 0,
+        }
+    }
+
+    #[test]
+    fn test_enqueue_dedup() {
+        let (queue, mut receiver) = MetadataQueue::new();
+        assert!(queue.enqueue(job((1, 1), 0, Weak::new())));
+        assert!(!queue.enqueue(job((1, 1), 0, Weak::new())));
+        assert!(queue.enqueue(job((1, 2), 0, Weak::new())));
+        assert!(queue.is_in_flight((1, 1)));
+        assert_eq!(receiver.try_recv().unwrap().key, (1, 1));
+        assert_eq!(receiver.try_recv().unwrap().key, (1, 2));
+        assert!(receiver.try_recv().is_err());
+
+        // 获取完成后可以再次加入队列
+        queue.finish((1, 1));
+        assert!(!queue.is_in_flight((1, 1)));
+        assert!(queue.enqueue(job((1, 1), 0, Weak::new())));
+    }
+
+    #[test]
+    fn test_priority_order() {
+        let mut pending = BinaryHeap::new();
+        for (seq, (key, priority)) in [((1, 1), 0), ((1, 2), 5), ((2, 1), 0), ((2, 2), 5)]
+            .into_iter()
+            .enumerate()
+        {
+            pending.push(PendingJob {
+                seq: seq as u64,
+                job: job(key, priority, Weak::new()),
+            });
+        }
+        let order: Vec<_> = std::iter::from_fn(|| pending.pop().map(|p| p.job.key)).collect();
+        assert_eq!(order, vec![(1, 2), (2, 2), (1, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let options = MetadataOptions::default();
+        assert_eq!(options.backoff(1), Duration::from_secs(30));
+        assert_eq!(options.backoff(2), Duration::from_secs(60));
+        assert_eq!(options.backoff(3), Duration::from_secs(120));
+        // 位移次数有上限，不会溢出
+        assert_eq!(options.backoff(100), options.backoff(17));
+        assert_eq!(options.backoff(0), options.backoff(1));
+    }
+
+    #[test]
+    fn test_failed_retry() {
+        let options = MetadataOptions::default();
+        let failed = |ago: u64| MetadataState::Failed {
+            attempts: 2,
+            error: "no peers".to_owned(),
+            time: Some(SystemTime::now() - Duration::from_secs(ago)),
+        };
+        // 等待时间为 60 秒
+        assert!(!failed(10).needs_fetch(&options));
+        assert!(failed(61).needs_fetch(&options));
+        assert!(!MetadataState::Fetched.needs_fetch(&options));
+        assert!(MetadataState::Queued.needs_fetch(&options));
+    }
+
+    #[tokio::test]
+    async fn test_retry_timeout() {
+        let item = item();
+        let job = job((1, 1), 0, Arc::downgrade(&item));
+        let semaphore = Arc::new(Semaphore::new(1));
+        let calls = AtomicU32::new(0);
+        let fetch = || {
+            calls.fetch_add(1, AtomicOrdering::SeqCst);
+            std::future::pending()
+        };
+        let result = fetch_with_retry(&job, None, &semaphore, &options(2), fetch).await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(AtomicOrdering::SeqCst), 3);
+        assert!(matches!(
+            &item.read().await.metadata,
+            MetadataState::Failed { attempts: 3, error, .. } if error == "Timed out after 0s"
+        ));
+        // 结束后释放并发名额
+        assert_eq!(semaphore.available_permits(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_success() {
+        let item = item();
+        let job = job((1, 1), 0, Arc::downgrade(&item));
+        let semaphore = Arc::new(Semaphore::new(1));
+        let calls = AtomicU32::new(0);
+        let fetch = || {
+            let attempt = calls.fetch_add(1, AtomicOrdering::SeqCst) + 1;
+            std::future::ready(if attempt < 2 {
+                Err(anyhow!("no peers"))
+            } else {
+                Ok(())
+            })
+        };
+        fetch_with_retry(&job, None, &semaphore, &options(3), fetch)
+            .await
+            .unwrap();
+        assert_eq!(calls.load(AtomicOrdering::SeqCst), 2);
+        assert_eq!(item.read().await.metadata, MetadataState::Fetched);
+    }
+}
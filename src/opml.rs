@@ -0,0 +1,268 @@
+use anyhow::Result;
+use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::name::ResolveResult;
+use quick_xml::reader::NsReader;
+use quick_xml::Writer;
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use std::time::Duration;
+
+use crate::retention::RetentionPolicy;
+use crate::rss::Rss;
+
+/// nekodl 私有属性所在的命名空间
+pub const NEKODL_NAMESPACE: &str = "https://github.com/Moeweb647252/nekodl";
+
+/// 保存在 `nekodl:settings` 属性中的订阅设置，以 JSON 形式序列化
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct OpmlSettings {
+    #[serde(default)]
+    pub auto_download: bool,
+    /// 更新间隔，单位为秒
+    #[serde(default)]
+    pub update_interval: Option<u64>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub adaptive_polling: bool,
+    #[serde(default)]
+    pub retention: RetentionPolicy,
+}
+
+impl OpmlSettings {
+    pub fn from_rss(rss: &Rss) -> Self {
+        Self {
+            auto_download: rss.auto_download,
+            update_interval: Some(rss.update_interval.as_secs()),
+            tags: rss.tags.clone(),
+            category: rss.category.clone(),
+            adaptive_polling: rss.adaptive_polling,
+            retention: rss.retention.clone(),
+        }
+    }
+
+    pub fn apply(&self, rss: &mut Rss) {
+        rss.auto_download = self.auto_download;
+        if let Some(interval) = self.update_interval {
+            rss.update_interval = Duration::from_secs(interval);
+        }
+        rss.tags = self.tags.clone();
+        rss.category = self.category.clone();
+        rss.adaptive_polling = self.adaptive_polling;
+        rss.retention = self.retention.clone();
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpmlFeed {
+    pub title: String,
+    pub url: String,
+    pub settings: Option<OpmlSettings>,
+}
+
+/// 无法导入的 outline，例如 `nekodl:settings` 无法解析
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidFeed {
+    pub title: String,
+    pub url: String,
+    pub error: String,
+}
+
+impl OpmlFeed {
+    pub fn from_rss(rss: &Rss) -> Self {
+        Self {
+            title: rss.title.clone(),
+            url: rss.url.clone(),
+            settings: Some(OpmlSettings::from_rss(rss)),
+        }
+    }
+}
+
+/// 将订阅列表导出为 OPML 2.0 文档
+pub fn export_opml(feeds: &[OpmlFeed]) -> Result<String> {
+    let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    writer.write_event(Event::Start(
+        BytesStart::new("opml")
+            .with_attributes([("version", "2.0"), ("xmlns:nekodl", NEKODL_NAMESPACE)]),
+    ))?;
+    writer.write_event(Event::Start(BytesStart::new("head")))?;
+    writer.write_event(Event::Start(BytesStart::new("title")))?;
+    writer.write_event(Event::Text(BytesText::new("nekodl subscriptions")))?;
+    writer.write_event(Event::End(BytesEnd::new("title")))?;
+    writer.write_event(Event::End(BytesEnd::new("head")))?;
+    writer.write_event(Event::Start(BytesStart::new("body")))?;
+    for feed in feeds {
+        let mut outline = BytesStart::new("outline").with_attributes([
+            ("type", "rss"),
+            ("text", feed.title.as_str()),
+            ("title", feed.title.as_str()),
+            ("xmlUrl", feed.url.as_str()),
+        ]);
+        if let Some(settings) = &feed.settings {
+            let settings = serde_json::to_string(settings)?;
+            outline.push_attribute(("nekodl:settings", settings.as_str()));
+        }
+        writer.write_event(Event::Empty(outline))?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("body")))?;
+    writer.write_event(Event::End(BytesEnd::new("opml")))?;
+    Ok(String::from_utf8(writer.into_inner().into_inner())?)
+}
+
+/// 解析 OPML 文档，返回其中所有带有 `xmlUrl` 的 outline，嵌套的分组会被展开。
+/// 单个 outline 无效时只影响该订阅，其余订阅照常返回
+pub fn parse_opml(content: &str) -> Result<Vec<Result<OpmlFeed, InvalidFeed>>> {
+    let mut reader = NsReader::from_str(content);
+    let mut feeds = Vec::new();
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"outline" => {
+                let mut title = None;
+                let mut text = None;
+                let mut url = None;
+                let mut settings = None;
+                let mut error = None;
+                for attr in e.attributes() {
+                    let attr = attr
+                        .map_err(anyhow::Error::from)
+                        .and_then(|attr| Ok((attr.key, attr.unescape_value()?.to_string())));
+                    let (key, value) = match attr {
+                        Ok(attr) => attr,
+                        Err(e) => {
+                            error = Some(e);
+                            break;
+                        }
+                    };
+                    match reader.resolve_attribute(key) {
+                        (ResolveResult::Bound(ns), local)
+                            if ns.as_ref() == NEKODL_NAMESPACE.as_bytes()
+                                && local.as_ref() == b"settings" =>
+                        {
+                            settings = Some(value);
+                        }
+                        (ResolveResult::Unbound, local) => match local.as_ref() {
+                            b"title" => title = Some(value),
+                            b"text" => text = Some(value),
+                            b"xmlUrl" => url = Some(value),
+                            _ => {}
+                        },
+                        _ => {}
+                    }
+                }
+                if let Some(e) = error {
+                    feeds.push(Err(InvalidFeed {
+                        title: title.or(text).or(url.clone()).unwrap_or_default(),
+                        url: url.unwrap_or_default(),
+                        error: format!("Invalid outline: {}", e),
+                    }));
+                    continue;
+                }
+                let Some(url) = url else {
+                    continue;
+                };
+                let title = title.or(text).unwrap_or_else(|| url.clone());
+                feeds.push(
+                    match settings.map(|s| serde_json::from_str(&s)).transpose() {
+                        Ok(settings) => Ok(OpmlFeed {
+                            title,
+                            url,
+                            settings,
+                        }),
+                        Err(e) => Err(InvalidFeed {
+                            title,
+                            url,
+                            error: format!("Invalid nekodl:settings: {}", e),
+                        }),
+                    },
+                );
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(feeds)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_opml_round_trip() {
+        let feeds = vec![
+            OpmlFeed {
+                title: "Mikan <Bangumi> & more".to_owned(),
+                url: "https://mikanani.me/RSS/Bangumi?bangumiId=3367&subgroupid=611".to_owned(),
+                settings: Some(OpmlSettings {
+                    auto_download: true,
+                    update_interval: Some(600),
+                    tags: vec!["airing".to_owned()],
+                    category: Some("anime".to_owned()),
+                    adaptive_polling: true,
+                    retention: RetentionPolicy {
+                        max_items: Some(200),
+                        handled_only: true,
+                        ..Default::default()
+                    },
+                }),
+            },
+            OpmlFeed {
+                title: "nyaa".to_owned(),
+                url: "https://nyaa.si/?page=rss".to_owned(),
+                settings: None,
+            },
+        ];
+        let content = export_opml(&feeds).unwrap();
+        let parsed: Vec<_> = parse_opml(&content)
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect();
+        assert_eq!(parsed, feeds);
+    }
+
+    #[test]
+    fn test_parse_foreign_opml() {
+        let content = r#"<?xml version="1.0"?>
+<opml version="1.0">
+  <body>
+    <outline text="Anime">
+      <outline text="Feed A" xmlUrl="https://example.com/a.xml"/>
+    </outline>
+    <outline text="Feed B" type="rss" xmlUrl="https://example.com/b.xml"></outline>
+  </body>
+</opml>"#;
+        let feeds: Vec<_> = parse_opml(content).unwrap().into_iter().flatten().collect();
+        assert_eq!(feeds.len(), 2);
+        assert_eq!(feeds[0].title, "Feed A");
+        assert_eq!(feeds[1].url, "https://example.com/b.xml");
+        assert!(feeds[1].settings.is_none());
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        let content = format!(
+            r#"<opml version="2.0" xmlns:nekodl="{}"><body>
+<outline text="A" xmlUrl="https://example.com/a.xml" nekodl:settings="not json"/>
+<outline text="B" xmlUrl="https://example.com/b.xml"/>
+<outline xmlUrl="https://example.com/c.xml" text="C &bogus;"/>
+</body></opml>"#,
+            NEKODL_NAMESPACE
+        );
+        let feeds = parse_opml(&content).unwrap();
+        assert_eq!(feeds.len(), 3);
+        assert_eq!(
+            feeds[0].as_ref().unwrap_err().url,
+            "https://example.com/a.xml"
+        );
+        assert_eq!(feeds[1].as_ref().unwrap().title, "B");
+        // 属性无法解析的 outline 也只影响该订阅
+        assert_eq!(
+            feeds[2].as_ref().unwrap_err().url,
+            "https://example.com/c.xml"
+        );
+    }
+}
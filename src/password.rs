@@ -0,0 +1,64 @@
+use anyhow::{anyhow, Result};
+use argon2::{password_hash::SaltString, Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use rand::rngs::OsRng;
+
+use crate::utils::sha256;
+
+/// 生成 Argon2id 哈希，格式为 PHC 字符串，包含随机盐和参数
+pub fn hash(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow!("Hash password: {}", e))?
+        .to_string())
+}
+
+/// 旧版本保存的是不加盐的 SHA-256，需要在登录成功后升级
+pub fn is_legacy(stored: &str) -> bool {
+    !stored.starts_with("$argon2")
+}
+
+/// 校验密码，同时支持旧版本的 SHA-256 哈希
+pub fn verify(stored: &str, password: &str) -> bool {
+    if is_legacy(stored) {
+        return !stored.is_empty() && stored.eq_ignore_ascii_case(&sha256(password));
+    }
+    PasswordHash::new(stored)
+        .map(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+/// 在阻塞线程中生成哈希，Argon2id 耗时较长，异步任务中应使用该函数
+pub async fn spawn_hash(password: String) -> Result<String> {
+    tokio::task::spawn_blocking(move || hash(&password)).await?
+}
+
+/// 在阻塞线程中校验密码
+pub async fn spawn_verify(stored: String, password: String) -> Result<bool> {
+    Ok(tokio::task::spawn_blocking(move || verify(&stored, &password)).await?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_verify() {
+        let stored = hash("secret").unwrap();
+        assert!(stored.starts_with("$argon2id$"));
+        assert_ne!(stored, hash("secret").unwrap());
+        assert!(verify(&stored, "secret"));
+        assert!(!verify(&stored, "Secret"));
+
+        let legacy = sha256("secret");
+        assert!(is_legacy(&legacy));
+        assert!(verify(&legacy, "secret"));
+        // 旧版本的哈希不能直接当作密码使用
+        assert!(!verify(&legacy, &legacy));
+        assert!(!verify("", ""));
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    cmp::Reverse,
+    collections::HashSet,
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::rss::{ItemKey, RssItem, RssItemStatus};
+
+/// 订阅条目的保留策略，所有条件都为空时不删除条目
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RetentionPolicy {
+    /// 删除发布时间早于该时长的条目，单位为秒
+    #[serde(default)]
+    pub max_age: Option<u64>,
+    /// 最多保留的条目数量，超出时删除最旧的条目
+    #[serde(default)]
+    pub max_items: Option<usize>,
+    /// 只删除已下载或已忽略的条目，未处理的条目总是保留
+    #[serde(default)]
+    pub handled_only: bool,
+}
+
+impl RetentionPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.max_age.is_some() || self.max_items.is_some()
+    }
+
+    /// 条目是否允许被删除，正在下载的条目总是保留
+    fn is_prunable(&self, status: &RssItemStatus) -> bool {
+        match status {
+            RssItemStatus::Downloading => false,
+            RssItemStatus::Downloaded | RssItemStatus::Ignored => true,
+            RssItemStatus::Unread | RssItemStatus::Read => !self.handled_only,
+        }
+    }
+}
+
+/// 已删除条目的标识，订阅源中仍存在该条目时不会被当作新条目再次加入
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Tombstone {
+    pub guid: Option<String>,
+    pub info_hash: Option<String>,
+    pub title: String,
+}
+
+impl Tombstone {
+    pub fn from_item(item: &RssItem) -> Self {
+        Self {
+            guid: item.guid.clone(),
+            info_hash: item.info_hash.clone(),
+            title: item.title.clone(),
+        }
+    }
+
+    pub fn key(&self) -> ItemKey<'_> {
+        ItemKey {
+            guid: self.guid.as_deref(),
+            info_hash: self.info_hash.as_deref(),
+            title: &self.title,
+        }
+    }
+}
+
+/// 按保留策略选出需要删除的条目。
+/// `items` 为 (条目 ID, 发布时间, 状态)，没有发布时间的条目视为最旧，同一时间时 ID 较大的较新。
+pub fn select_pruned(
+    policy: &RetentionPolicy,
+    items: &[(usize, Option<SystemTime>, RssItemStatus)],
+    now: SystemTime,
+) -> HashSet<usize> {
+    let mut pruned = HashSet::new();
+    if let Some(max_age) = policy.max_age {
+        let max_age = Duration::from_secs(max_age);
+        for (id, time, status) in items {
+            let expired =
+                time.is_none_or(|time| now.duration_since(time).unwrap_or_default() > max_age);
+            if expired && policy.is_prunable(status) {
+                pruned.insert(*id);
+            }
+        }
+    }
+    if let Some(max_items) = policy.max_items {
+        let mut remaining: Vec<_> = items
+            .iter()
+            .filter(|(id, ..)| !pruned.contains(id))
+            .collect();
+        remaining.sort_by_key(|(id, time, _)| Reverse((*time, *id)));
+        for (id, _, status) in remaining.into_iter().skip(max_items) {
+            if policy.is_prunable(status) {
+                pruned.insert(*id);
+            }
+        }
+    }
+    pruned
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    fn time(day: u64) -> Option<SystemTime> {
+        Some(UNIX_EPOCH + Duration::from_secs(day * 24 * 3600))
+    }
+
+    #[test]
+    fn test_select_pruned() {
+        let items = vec![
+            (0, time(1), RssItemStatus::Downloaded),
+            (1, time(2), RssItemStatus::Read),
+            (2, time(3), RssItemStatus::Downloading),
+            (3, time(9), RssItemStatus::Ignored),
+            (4, time(10), RssItemStatus::Unread),
+        ];
+        let now = time(10).unwrap();
+
+        let policy = RetentionPolicy {
+            max_age: Some(5 * 24 * 3600),
+            ..Default::default()
+        };
+        assert_eq!(select_pruned(&policy, &items, now), HashSet::from([0, 1]));
+
+        let policy = RetentionPolicy {
+            max_items: Some(2),
+            handled_only: true,
+            ..Default::default()
+        };
+        assert_eq!(select_pruned(&policy, &items, now), HashSet::from([0]));
+
+        let policy = RetentionPolicy {
+            max_items: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            select_pruned(&policy, &items, now),
+            HashSet::from([0, 1, 3])
+        );
+
+        assert!(select_pruned(&RetentionPolicy::default(), &items, now).is_empty());
+    }
+}
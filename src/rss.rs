@@ -1,10 +1,15 @@
 //use crate::download::item_downaload_task;
+use crate::calendar::{feed_releases, next_poll_delay, predict};
+use crate::downloader::rqbit::item_downaload_task;
+use crate::item_meta::{parse_mikan_torrents, ItemMeta, MikanTorrent};
+use crate::metadata::{MetadataJob, MetadataState};
+use crate::retention::{select_pruned, RetentionPolicy, Tombstone};
 use crate::state::{Config, SerdeLockLayer, State};
-use crate::torrent::fetch_torrent_for_item;
-use anyhow::{Context, Result};
-use librqbit::AddTorrent;
+use anyhow::{anyhow, Context, Result};
+use librqbit::Session;
 use rss::Channel;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::sync::{Arc, Weak};
 use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
@@ -17,11 +22,60 @@ pub struct RssItem {
     pub description: String,
     pub status: RssItemStatus,
     pub torrent: Option<ItemTorrent>,
+    #[serde(default)]
+    pub metadata: MetadataState,
+    /// 种子的 infohash，种子文件缓存以此为键
+    #[serde(default)]
+    pub info_hash: Option<String>,
+    /// 下载完成的时间
+    #[serde(default)]
+    pub downloaded_time: Option<std::time::SystemTime>,
+    /// 首次获取到该条目的时间
+    #[serde(default)]
+    pub add_time: Option<std::time::SystemTime>,
+    /// 订阅源中的 guid，用于判断条目是否重复
+    #[serde(default)]
+    pub guid: Option<String>,
+    /// 订阅源中的发布时间
+    #[serde(default)]
+    pub pub_date: Option<std::time::SystemTime>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    /// 种子内容的总大小，单位为字节，订阅源提供时无需获取元数据
+    #[serde(default)]
+    pub size: Option<u64>,
+    /// 最近一次更新订阅时的做种人数
+    #[serde(default)]
+    pub seeders: Option<u32>,
     pub id: usize,
     #[serde(skip)]
     pub download_handle: Option<Arc<JoinHandle<Result<()>>>>,
 }
 
+/// 用于判断两个条目是否为同一条目的标识
+pub struct ItemKey<'a> {
+    pub guid: Option<&'a str>,
+    pub info_hash: Option<&'a str>,
+    pub title: &'a str,
+}
+
+impl ItemKey<'_> {
+    /// infohash 相同，或 guid 相同，没有 guid 时比较标题
+    pub fn same_as(&self, other: &ItemKey) -> bool {
+        if let (Some(a), Some(b)) = (self.info_hash, other.info_hash) {
+            if a == b {
+                return true;
+            }
+        }
+        match (self.guid, other.guid) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.title == other.title,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ItemTorrent {
     pub files: Vec<TorrentFileInfo>,
@@ -41,6 +95,52 @@ pub enum RssItemStatus {
     Read,
     Downloading,
     Downloaded,
+    /// 已忽略，自动下载不会处理该项
+    Ignored,
+}
+
+impl RssItemStatus {
+    /// 是否允许由用户手动设置该状态，下载相关的状态只由下载任务维护
+    pub fn is_user_settable(&self) -> bool {
+        matches!(self, Self::Unread | Self::Read | Self::Ignored)
+    }
+}
+
+/// 条目筛选条件，用于批量操作，所有字段为空时匹配全部条目
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ItemFilter {
+    pub rss_id: Option<usize>,
+    /// 订阅的标签或分类
+    pub tag: Option<String>,
+    pub item_ids: Option<Vec<usize>>,
+    pub status: Option<RssItemStatus>,
+    pub keyword: Option<String>,
+}
+
+impl ItemFilter {
+    pub fn matches_rss(&self, rss: &Rss) -> bool {
+        self.rss_id.map_or(true, |id| id == rss.id)
+            && self.tag.as_ref().map_or(true, |tag| rss.has_tag(tag))
+    }
+
+    pub fn matches_item(&self, item: &RssItem) -> bool {
+        if let Some(ids) = &self.item_ids {
+            if !ids.contains(&item.id) {
+                return false;
+            }
+        }
+        if let Some(status) = &self.status {
+            if status != &item.status {
+                return false;
+            }
+        }
+        if let Some(keyword) = &self.keyword {
+            if !item.title.to_lowercase().contains(&keyword.to_lowercase()) {
+                return false;
+            }
+        }
+        true
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -57,14 +157,56 @@ pub struct Rss {
     pub url: String,
     pub title: String,
     pub description: String,
+    /// 条目单独保存，不随订阅一起序列化
+    #[serde(skip)]
     pub items: Vec<SerdeLockLayer<RssItem>>,
     pub update_time: std::time::SystemTime,
     pub update_interval: std::time::Duration,
     pub status: RssStatus,
     pub auto_download: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 所属分类，对应 `Config.categories` 的键
+    #[serde(default)]
+    pub category: Option<String>,
+    /// 根据推断出的放送时间调整轮询间隔
+    #[serde(default)]
+    pub adaptive_polling: bool,
+    /// 下一个条目的 ID，删除条目后条目 ID 不再等于其在列表中的位置
+    #[serde(default)]
+    pub item_id_index: usize,
+    #[serde(default)]
+    pub retention: RetentionPolicy,
+    /// 已删除条目的标识，条目从订阅源中消失后随之清除
+    #[serde(default)]
+    pub tombstones: Vec<Tombstone>,
+    /// 添加订阅的用户，为空时只有管理员可以修改
+    #[serde(default)]
+    pub owner: Option<String>,
 }
 
 impl Rss {
+    pub fn new(id: usize, url: String, title: String, description: String) -> Self {
+        Self {
+            id,
+            url,
+            title,
+            description,
+            items: Vec::new(),
+            update_time: std::time::SystemTime::now(),
+            update_interval: std::time::Duration::from_secs(3600),
+            status: RssStatus::Created,
+            auto_download: false,
+            tags: Vec::new(),
+            category: None,
+            adaptive_polling: false,
+            item_id_index: 0,
+            retention: RetentionPolicy::default(),
+            tombstones: Vec::new(),
+            owner: None,
+        }
+    }
+
     pub fn info(&self) -> Self {
         Self {
             items: Vec::new(),
@@ -76,14 +218,136 @@ impl Rss {
             update_interval: self.update_interval,
             status: RssStatus::Created,
             auto_download: self.auto_download,
+            tags: self.tags.clone(),
+            category: self.category.clone(),
+            adaptive_polling: self.adaptive_polling,
+            item_id_index: self.item_id_index,
+            retention: self.retention.clone(),
+            tombstones: Vec::new(),
+            owner: self.owner.clone(),
+        }
+    }
+
+    /// 分配新条目的 ID
+    pub fn next_item_id(&mut self) -> usize {
+        // 旧版本的数据没有保存计数器，此时条目 ID 与位置相同
+        self.item_id_index = self.item_id_index.max(self.items.len());
+        let id = self.item_id_index;
+        self.item_id_index += 1;
+        id
+    }
+
+    /// 按 ID 查找条目，条目按 ID 从小到大排列
+    pub async fn find_item(&self, id: usize) -> Option<&SerdeLockLayer<RssItem>> {
+        let (mut low, mut high) = (0, self.items.len());
+        while low < high {
+            let mid = (low + high) / 2;
+            let mid_id = self.items[mid].read().await.id;
+            match mid_id.cmp(&id) {
+                std::cmp::Ordering::Equal => return Some(&self.items[mid]),
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+            }
+        }
+        None
+    }
+
+    /// 按保留策略删除条目并留下标识，返回被删除条目的 ID
+    pub async fn prune(&mut self, now: std::time::SystemTime) -> Vec<usize> {
+        if !self.retention.is_enabled() {
+            return Vec::new();
+        }
+        let mut candidates = Vec::new();
+        for item in self.items.iter() {
+            let item = item.read().await;
+            // 下载任务仍持有句柄的条目视为正在下载
+            let status = if item.download_handle.is_some() {
+                RssItemStatus::Downloading
+            } else {
+                item.status.clone()
+            };
+            candidates.push((item.id, item.publish_time(), status));
+        }
+        let pruned = select_pruned(&self.retention, &candidates, now);
+        if pruned.is_empty() {
+            return Vec::new();
+        }
+        let mut kept = Vec::with_capacity(self.items.len() - pruned.len());
+        for item in self.items.drain(..) {
+            let guard = item.read().await;
+            if pruned.contains(&guard.id) {
+                self.tombstones.push(Tombstone::from_item(&guard));
+            } else {
+                drop(guard);
+                kept.push(item);
+            }
         }
+        self.items = kept;
+        let mut pruned: Vec<usize> = pruned.into_iter().collect();
+        pruned.sort();
+        pruned
+    }
+
+    /// 订阅是否带有该标签，分类也视为标签
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag) || self.category.as_deref() == Some(tag)
+    }
+
+    /// 订阅的下载目录，分类设置了下载目录时使用分类的目录
+    pub fn output_dir(&self, config: &Config) -> PathBuf {
+        let mut path = PathBuf::from(
+            config
+                .category(self.category.as_deref())
+                .and_then(|c| c.output_path.as_ref())
+                .unwrap_or(&config.output_path),
+        );
+        path.push(&self.title);
+        path
+    }
+
+    /// 统计未读条目数量
+    pub async fn unread_count(&self) -> usize {
+        let mut count = 0;
+        for item in self.items.iter() {
+            if item.read().await.status == RssItemStatus::Unread {
+                count += 1;
+            }
+        }
+        count
+    }
+}
+
+/// 获取订阅源的超时时间
+const FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// 检查订阅的更新间隔，间隔为 0 时订阅会被不停地轮询
+pub fn check_update_interval(interval: Option<u64>) -> Result<()> {
+    if interval == Some(0) {
+        return Err(anyhow!("update_interval must be positive"));
+    }
+    Ok(())
+}
+
+/// 检查订阅的分类是否存在
+pub fn check_category(config: &Config, category: Option<&str>) -> Result<()> {
+    match category {
+        Some(category) if !config.categories.contains_key(category) => {
+            Err(anyhow!("Category {} not found", category))
+        }
+        _ => Ok(()),
     }
 }
 
 pub async fn fetch_channel(link: &str) -> Result<Channel> {
-    let client = reqwest::Client::new();
+    Ok(fetch_feed(link).await?.0)
+}
+
+/// 获取订阅源，同时解析 Mikan 的 `<torrent>` 元素，返回值与频道中的条目一一对应
+pub async fn fetch_feed(link: &str) -> Result<(Channel, Vec<Option<MikanTorrent>>)> {
+    let client = reqwest::Client::builder().timeout(FETCH_TIMEOUT).build()?;
     let content = client.get(link).send().await?.bytes().await?;
-    Ok(Channel::read_from(&content[..])?)
+    let channel = Channel::read_from(&content[..])?;
+    Ok((channel, parse_mikan_torrents(&content)))
 }
 
 // 定义一个异步函数rss_task，用于更新RSS源并发送更新事件
@@ -101,14 +365,25 @@ pub async fn rss_task(
         } else {
             break;
         };
-        // 如果距离上次更新时间小于更新间隔，并且RSS状态不是已创建，则等待剩余时间
-        if rss.update_time.elapsed().unwrap() < rss.update_interval
-            && rss.status != RssStatus::Created
-        {
-            sleep(rss.update_interval - rss.update_time.elapsed().unwrap()).await;
+        // 如果RSS状态不是已创建，则等待到下一次轮询，开启自适应轮询时在预计放送时间附近缩短间隔
+        if rss.status != RssStatus::Created {
+            let prediction = if rss.adaptive_polling {
+                predict(&feed_releases(&rss.items).await)
+            } else {
+                None
+            };
+            let delay = next_poll_delay(
+                rss.update_interval,
+                rss.update_time.elapsed().unwrap_or_default(),
+                prediction.as_ref(),
+                std::time::SystemTime::now(),
+            );
+            if !delay.is_zero() {
+                sleep(delay).await;
+            }
         }
         // 异步获取RSS源的频道信息
-        let channel = fetch_channel(&rss.url).await.unwrap();
+        let (channel, mikan_torrents) = fetch_feed(&rss.url).await.unwrap();
         // 初始化一个向量用于存储RSS项
         let mut items = Vec::new();
         // 遍历频道中的每一项
@@ -119,9 +394,11 @@ pub async fn rss_task(
             } else {
                 "Default Title".to_owned()
             };
-            // 获取链接，如果没有链接则跳过该项
+            // 获取链接，优先使用 enclosure，nyaa 等订阅源直接使用条目链接，都没有则跳过该项
             let link = if let Some(link) = &item.1.enclosure {
                 link.url.to_owned()
+            } else if let Some(link) = item.1.link() {
+                link.to_owned()
             } else {
                 continue;
             };
@@ -131,6 +408,10 @@ pub async fn rss_task(
             } else {
                 "Default Description".to_owned()
             };
+            let mut meta = ItemMeta::from_item(item.1);
+            if let Some(Some(torrent)) = mikan_torrents.get(item.0) {
+                meta.merge_mikan(torrent);
+            }
             // 将获取到的RSS项信息添加到向量中
             items.push(RssItem {
                 title,
@@ -139,28 +420,68 @@ pub async fn rss_task(
                 status: RssItemStatus::Unread,
                 id: item.0,
                 torrent: None,
+                metadata: MetadataState::default(),
+                info_hash: meta.info_hash,
+                downloaded_time: None,
+                add_time: Some(std::time::SystemTime::now()),
+                guid: meta.guid,
+                pub_date: meta.pub_date,
+                categories: meta.categories,
+                author: meta.author,
+                size: meta.size,
+                seeders: meta.seeders,
                 download_handle: None,
             });
         }
         for i in rss.items.iter() {
-            let tmp = i.read().await;
+            let mut tmp = i.write().await;
             if let Some(pos) = items.iter().position(|x| tmp.comprare(x)) {
-                items.remove(pos);
+                // 已有条目补全订阅源新提供的信息
+                tmp.update_from(&items.remove(pos));
             }
         }
-        let mut index = rss.items.len();
-        items.iter_mut().for_each(|v| {
-            v.id = index;
-            index += 1;
+        // 已删除的条目不再加入，订阅源中已经没有对应条目的标识随之清除
+        let mut seen = vec![false; rss.tombstones.len()];
+        items.retain(|x| {
+            match rss
+                .tombstones
+                .iter()
+                .position(|t| t.key().same_as(&x.key()))
+            {
+                Some(pos) => {
+                    seen[pos] = true;
+                    false
+                }
+                None => true,
+            }
         });
+        let stale: Vec<&Tombstone> = if channel.items().is_empty() {
+            Vec::new()
+        } else {
+            rss.tombstones
+                .iter()
+                .zip(seen)
+                .filter(|(_, seen)| !seen)
+                .map(|(t, _)| t)
+                .collect()
+        };
 
         if let Some(lock) = rss_lock.upgrade() {
+            let search_index = state.read().await.search_index.clone();
             {
                 let mut guard = lock.write().await;
+                let mut search_index = search_index.write().await;
                 guard.update_time = std::time::SystemTime::now();
-                for i in items {
+                for mut i in items {
+                    i.id = guard.next_item_id();
+                    // 新条目加入全文索引
+                    search_index.add_item((rss.id, i.id), &i);
                     guard.items.push(i.into());
                 }
+                guard.tombstones.retain(|t| !stale.contains(&t));
+                for id in guard.prune(std::time::SystemTime::now()).await {
+                    search_index.remove((rss.id, id));
+                }
                 guard.status = RssStatus::Updated;
             }
             let guard = lock.read().await;
@@ -171,6 +492,16 @@ pub async fn rss_task(
                 .clone()
                 .context("librqbit session not found")
                 .unwrap();
+            let metadata_queue = state.read().await.metadata_queue.clone();
+            let (priority, metadata_options) = {
+                let config = config.read().await;
+                (
+                    config
+                        .category(rss.category.as_deref())
+                        .map_or(0, |c| c.priority),
+                    config.metadata_options.clone(),
+                )
+            };
             for i in guard.items.iter() {
                 let session = session.clone();
                 let mut item = i.write().await;
@@ -178,30 +509,32 @@ pub async fn rss_task(
                     if rss.auto_download {
                         if item.download_handle.is_none()
                             && item.status != RssItemStatus::Downloaded
+                            && item.status != RssItemStatus::Ignored
                         {
                             /*let handle = tokio::spawn(item_downaload_task(
                                 session.clone(),
                                 i.weak(),
-                                rss.title.clone(),
+                                rss.info(),
                                 config.clone(),
                             ));
                             item.status = RssItemStatus::Downloading;
                             item.download_handle = Some(Arc::new(handle));*/
                         }
                     }
-                } else {
-                    let weak = i.weak();
-                    let trackers = config.read().await.torrent_options.trackers.clone();
-                    let link = item.link.clone();
-                    tokio::spawn(async move {
-                        fetch_torrent_for_item(
-                            AddTorrent::Url(link.into()),
-                            session.clone(),
-                            trackers,
-                            weak,
-                        )
-                        .await
+                } else if item.metadata.needs_fetch(&metadata_options)
+                    // 订阅源已提供 infohash 和大小的条目无需获取元数据
+                    && (item.info_hash.is_none() || item.size.is_none())
+                {
+                    // 交给元数据队列获取，已在队列中的条目不会重复加入
+                    let queued = metadata_queue.enqueue(MetadataJob {
+                        key: (rss.id, item.id),
+                        item: i.weak(),
+                        category: rss.category.clone(),
+                        priority,
                     });
+                    if queued {
+                        item.metadata = MetadataState::Queued;
+                    }
                 }
             }
         } else {
@@ -210,9 +543,77 @@ pub async fn rss_task(
     }
 }
 
+/// 开始下载条目，条目正在下载或已下载时返回错误
+pub async fn start_item_download(
+    item: &SerdeLockLayer<RssItem>,
+    rss: &Rss,
+    session: Arc<Session>,
+    config: Arc<RwLock<Config>>,
+) -> Result<()> {
+    let mut guard = item.write().await;
+    if guard.download_handle.is_some()
+        || matches!(
+            guard.status,
+            RssItemStatus::Downloading | RssItemStatus::Downloaded
+        )
+    {
+        return Err(anyhow!("Item {} is {:?}", guard.id, guard.status));
+    }
+    let handle = tokio::spawn(item_downaload_task(
+        session,
+        item.weak(),
+        rss.info(),
+        config,
+    ));
+    guard.status = RssItemStatus::Downloading;
+    guard.download_handle = Some(Arc::new(handle));
+    Ok(())
+}
+
 impl RssItem {
+    pub fn key(&self) -> ItemKey<'_> {
+        ItemKey {
+            guid: self.guid.as_deref(),
+            info_hash: self.info_hash.as_deref(),
+            title: &self.title,
+        }
+    }
+
     pub fn comprare(&self, item: &Self) -> bool {
-        self.title == item.title
+        self.key().same_as(&item.key())
+    }
+
+    /// 用重新获取到的同一条目更新订阅源提供的信息，不覆盖已有的值，做种人数总是更新
+    pub fn update_from(&mut self, item: &Self) {
+        self.guid = self.guid.take().or(item.guid.clone());
+        self.pub_date = self.pub_date.or(item.pub_date);
+        if self.categories.is_empty() {
+            self.categories = item.categories.clone();
+        }
+        self.author = self.author.take().or(item.author.clone());
+        self.size = self.size.or(item.size);
+        self.info_hash = self.info_hash.take().or(item.info_hash.clone());
+        self.seeders = item.seeders.or(self.seeders);
+    }
+
+    /// 条目的发布时间，用于排序和按日期筛选，订阅源没有提供时使用首次获取的时间
+    pub fn publish_time(&self) -> Option<std::time::SystemTime> {
+        self.pub_date.or(self.add_time)
+    }
+
+    /// 由用户修改条目状态，返回状态是否发生变化
+    pub fn set_user_status(&mut self, status: RssItemStatus) -> Result<bool> {
+        if !status.is_user_settable() {
+            return Err(anyhow!("Status {:?} can not be set manually", status));
+        }
+        if !self.status.is_user_settable() {
+            return Err(anyhow!("Item {} is {:?}", self.id, self.status));
+        }
+        if self.status == status {
+            return Ok(false);
+        }
+        self.status = status;
+        Ok(true)
     }
 }
 
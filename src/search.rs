@@ -0,0 +1,226 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{episode::parse_title, rss::RssItem};
+
+/// (rss id, item id)
+pub type DocId = (usize, usize);
+
+/// BM25 参数
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+/// 条目的全文索引，在内存中维护，随 `rss_task` 添加条目增量更新
+#[derive(Default)]
+pub struct SearchIndex {
+    /// 词 -> 文档 -> 加权词频
+    postings: HashMap<String, HashMap<DocId, u32>>,
+    /// 文档 -> (包含的词, 加权长度)，用于删除文档和长度归一化
+    docs: HashMap<DocId, (Vec<String>, u32)>,
+    total_len: u64,
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}' // 平假名、片假名
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{f900}'..='\u{faff}'
+        | '\u{ac00}'..='\u{d7af}' // 韩文
+    )
+}
+
+/// 分词：连续的字母数字作为一个词，中日韩文字按相邻两字切分
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut cjk: Vec<char> = Vec::new();
+    let flush_cjk = |cjk: &mut Vec<char>, tokens: &mut Vec<String>| {
+        match cjk.len() {
+            0 => {}
+            1 => tokens.push(cjk[0].to_string()),
+            _ => tokens.extend(cjk.windows(2).map(|w| w.iter().collect::<String>())),
+        }
+        cjk.clear();
+    };
+    for c in text.chars().flat_map(|c| c.to_lowercase()) {
+        if is_cjk(c) {
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+            cjk.push(c);
+        } else if c.is_alphanumeric() {
+            flush_cjk(&mut cjk, &mut tokens);
+            word.push(c);
+        } else {
+            flush_cjk(&mut cjk, &mut tokens);
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+        }
+    }
+    flush_cjk(&mut cjk, &mut tokens);
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+    tokens
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    /// 索引一个条目，标题和解析出的剧集信息的权重高于描述
+    pub fn add_item(&mut self, doc: DocId, item: &RssItem) {
+        let episode = parse_title(&item.title);
+        let metadata = [
+            episode.group.unwrap_or_default(),
+            episode.title.unwrap_or_default(),
+            episode.resolution.unwrap_or_default(),
+        ]
+        .join(" ");
+        self.add(
+            doc,
+            &[
+                (item.title.as_str(), 3),
+                (metadata.as_str(), 2),
+                (item.description.as_str(), 1),
+            ],
+        );
+    }
+
+    /// 索引一个文档，`fields` 为 (文本, 权重)，已存在的文档会被替换
+    pub fn add(&mut self, doc: DocId, fields: &[(&str, u32)]) {
+        self.remove(doc);
+        let mut freqs: HashMap<String, u32> = HashMap::new();
+        let mut len = 0;
+        for (text, weight) in fields {
+            for token in tokenize(text) {
+                *freqs.entry(token).or_default() += weight;
+                len += weight;
+            }
+        }
+        for (token, freq) in freqs.iter() {
+            self.postings
+                .entry(token.clone())
+                .or_default()
+                .insert(doc, *freq);
+        }
+        self.total_len += len as u64;
+        self.docs.insert(doc, (freqs.into_keys().collect(), len));
+    }
+
+    pub fn remove(&mut self, doc: DocId) {
+        let Some((tokens, len)) = self.docs.remove(&doc) else {
+            return;
+        };
+        self.total_len -= len as u64;
+        for token in tokens {
+            if let Some(posting) = self.postings.get_mut(&token) {
+                posting.remove(&doc);
+                if posting.is_empty() {
+                    self.postings.remove(&token);
+                }
+            }
+        }
+    }
+
+    /// 删除某个订阅的所有条目
+    pub fn remove_rss(&mut self, rss_id: usize) {
+        let docs: Vec<DocId> = self
+            .docs
+            .keys()
+            .filter(|doc| doc.0 == rss_id)
+            .copied()
+            .collect();
+        for doc in docs {
+            self.remove(doc);
+        }
+    }
+
+    /// 搜索包含所有查询词的文档，按 BM25 得分从高到低排序
+    pub fn search(&self, query: &str) -> Vec<(DocId, f64)> {
+        let tokens: HashSet<String> = tokenize(query).into_iter().collect();
+        if tokens.is_empty() || self.docs.is_empty() {
+            return Vec::new();
+        }
+        let mut postings = Vec::new();
+        for token in tokens.iter() {
+            match self.postings.get(token) {
+                Some(posting) => postings.push(posting),
+                None => return Vec::new(),
+            }
+        }
+        // 从最短的倒排列表开始求交集
+        postings.sort_by_key(|p| p.len());
+        let n = self.docs.len() as f64;
+        let avg_len = self.total_len as f64 / n;
+        let mut results: Vec<(DocId, f64)> = postings[0]
+            .keys()
+            .filter(|doc| postings[1..].iter().all(|p| p.contains_key(doc)))
+            .map(|doc| {
+                let len = self.docs[doc].1 as f64;
+                let score = postings
+                    .iter()
+                    .map(|posting| {
+                        let df = posting.len() as f64;
+                        let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+                        let tf = posting[doc] as f64;
+                        idf * tf * (K1 + 1.0) / (tf + K1 * (1.0 - B + B * len / avg_len))
+                    })
+                    .sum();
+                (*doc, score)
+            })
+            .collect();
+        results.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| b.0.cmp(&a.0)));
+        results
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("[ANi] 葬送的芙莉蓮 - 05 [1080P]"),
+            vec!["ani", "葬送", "送的", "的芙", "芙莉", "莉蓮", "05", "1080p"]
+        );
+        assert_eq!(tokenize("第2季"), vec!["第", "2", "季"]);
+    }
+
+    #[test]
+    fn test_search() {
+        let mut index = SearchIndex::new();
+        index.add((1, 0), &[("[ANi] 葬送的芙莉蓮 - 05 [1080P]", 3)]);
+        index.add((1, 1), &[("[ANi] 葬送的芙莉蓮 - 06 [1080P]", 3)]);
+        index.add(
+            (2, 0),
+            &[("[LoliHouse] Frieren - 05 [720p]", 3), ("葬送的芙莉莲", 1)],
+        );
+
+        let hits: Vec<DocId> = index.search("芙莉蓮 05").into_iter().map(|h| h.0).collect();
+        assert_eq!(hits, vec![(1, 0)]);
+
+        let hits = index.search("05");
+        assert_eq!(hits.len(), 2);
+
+        // 标题中的词权重更高
+        let hits = index.search("葬送");
+        assert_eq!(hits.len(), 3);
+        assert_eq!(hits[2].0, (2, 0));
+
+        index.remove_rss(1);
+        assert_eq!(index.len(), 1);
+        assert!(index.search("ani").is_empty());
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::RwLock, time::sleep};
+use tracing::{error, info};
+
+use crate::{
+    episode::{parse_title, EpisodeInfo},
+    rss::{start_item_download, Rss, RssItem, RssItemStatus},
+    state::{Config, DataBase, SerdeLockLayer, State},
+};
+
+/// 剧集，汇总多个订阅中同一部作品的条目
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Series {
+    pub id: usize,
+    pub name: String,
+    /// 关联的订阅
+    pub rss_ids: Vec<usize>,
+    /// 条目标题需要包含的关键词（不区分大小写），为空时关联订阅的所有条目都属于该剧集
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// 只统计该季的条目，标题中没有季数的条目视为匹配
+    #[serde(default)]
+    pub season: Option<u32>,
+    /// 总集数，已知时用于判断缺集
+    #[serde(default)]
+    pub total_episodes: Option<u32>,
+    /// 发现缺集且关联订阅中有对应条目时自动下载
+    #[serde(default)]
+    pub auto_fill: bool,
+    /// 创建剧集的用户，为空时只有管理员可以修改
+    #[serde(default)]
+    pub owner: Option<String>,
+}
+
+impl Series {
+    pub fn matches(&self, item: &RssItem, episode: &EpisodeInfo) -> bool {
+        let title = item.title.to_lowercase();
+        if !self
+            .keywords
+            .iter()
+            .all(|k| title.contains(&k.to_lowercase()))
+        {
+            return false;
+        }
+        match (self.season, episode.season) {
+            (Some(a), Some(b)) => a == b,
+            _ => true,
+        }
+    }
+}
+
+/// 某一集在关联订阅中的来源条目
+#[derive(Serialize, Clone, Debug)]
+pub struct EpisodeSource {
+    pub rss_id: usize,
+    pub item_id: usize,
+    pub title: String,
+    pub status: RssItemStatus,
+    pub group: Option<String>,
+    pub resolution: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct EpisodeStatus {
+    pub episode: u32,
+    pub downloaded: bool,
+    pub sources: Vec<EpisodeSource>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SeriesReport {
+    pub series: Series,
+    pub episodes: Vec<EpisodeStatus>,
+    /// 没有下载（也不在下载中）的集数
+    pub missing: Vec<u32>,
+    /// 下载了多个版本的集数
+    pub duplicates: Vec<u32>,
+}
+
+/// 收集关联订阅中属于该剧集的条目，按集数分组，合集会展开到其包含的每一集
+pub async fn collect_episodes(
+    series: &Series,
+    rss_list: &HashMap<usize, SerdeLockLayer<Rss>>,
+) -> BTreeMap<u32, Vec<EpisodeSource>> {
+    let mut episodes: BTreeMap<u32, Vec<EpisodeSource>> = BTreeMap::new();
+    for rss_id in series.rss_ids.iter() {
+        let Some(rss) = rss_list.get(rss_id) else {
+            continue;
+        };
+        let rss = rss.read().await;
+        for item in rss.items.iter() {
+            let item = item.read().await;
+            let info = parse_title(&item.title);
+            let Some(first) = info.episode else {
+                continue;
+            };
+            if !series.matches(&item, &info) {
+                continue;
+            }
+            for episode in first..=info.episode_end.unwrap_or(first).max(first) {
+                episodes.entry(episode).or_default().push(EpisodeSource {
+                    rss_id: rss.id,
+                    item_id: item.id,
+                    title: item.title.clone(),
+                    status: item.status.clone(),
+                    group: info.group.clone(),
+                    resolution: info.resolution.clone(),
+                });
+            }
+        }
+    }
+    episodes
+}
+
+/// 根据收集到的条目判断已下载、缺失和重复的集数。
+/// 判断缺集的范围为 1 到总集数，总集数未知时为出现过的最大集数。
+pub fn analyze(series: Series, mut episodes: BTreeMap<u32, Vec<EpisodeSource>>) -> SeriesReport {
+    let max_seen = episodes.keys().next_back().copied().unwrap_or(0);
+    let last = series.total_episodes.unwrap_or(max_seen);
+    let mut missing = Vec::new();
+    let mut duplicates = Vec::new();
+    let mut statuses = Vec::new();
+    // 第 0 集（如 SP、前导）不参与缺集判断
+    for episode in 0..=last.max(max_seen) {
+        let sources = episodes.remove(&episode).unwrap_or_default();
+        let downloaded = sources
+            .iter()
+            .filter(|s| s.status == RssItemStatus::Downloaded)
+            .count();
+        let downloading = sources
+            .iter()
+            .any(|s| s.status == RssItemStatus::Downloading);
+        let expected = episode >= 1 && episode <= last;
+        if downloaded > 1 {
+            duplicates.push(episode);
+        }
+        if expected && downloaded == 0 && !downloading {
+            missing.push(episode);
+        }
+        if expected || !sources.is_empty() {
+            statuses.push(EpisodeStatus {
+                episode,
+                downloaded: downloaded > 0,
+                sources,
+            });
+        }
+    }
+    SeriesReport {
+        series,
+        episodes: statuses,
+        missing,
+        duplicates,
+    }
+}
+
+/// 选择用于补全某一集的条目：只考虑未下载且未忽略的条目，优先选择分辨率最高的条目
+pub fn pick_source(sources: &[EpisodeSource]) -> Option<&EpisodeSource> {
+    sources
+        .iter()
+        .filter(|s| s.status == RssItemStatus::Unread || s.status == RssItemStatus::Read)
+        .max_by_key(|s| {
+            s.resolution
+                .as_deref()
+                .and_then(|r| r.trim_end_matches('p').parse::<u32>().ok())
+                .unwrap_or(0)
+        })
+}
+
+/// 从关联订阅中下载某一集，返回开始下载的条目
+pub async fn fetch_episode(
+    series: &Series,
+    episode: u32,
+    db: &DataBase,
+    state: &Arc<RwLock<State>>,
+    config: &Arc<RwLock<Config>>,
+) -> Result<EpisodeSource> {
+    let episodes = collect_episodes(series, &db.rss_list).await;
+    let source = episodes
+        .get(&episode)
+        .and_then(|sources| pick_source(sources))
+        .cloned()
+        .context("Episode source")?;
+    let session = state
+        .read()
+        .await
+        .rqbit_session
+        .clone()
+        .context("librqbit session not found")?;
+    let rss = db.rss_list.get(&source.rss_id).context("Rss")?.read().await;
+    let item = rss.find_item(source.item_id).await.context("Item")?;
+    start_item_download(item, &rss, session, config.clone()).await?;
+    Ok(source)
+}
+
+/// 定期检查开启了自动补全的剧集，下载缺失且有来源的集数
+pub async fn series_task(
+    db: Arc<RwLock<DataBase>>,
+    state: Arc<RwLock<State>>,
+    config: Arc<RwLock<Config>>,
+) {
+    loop {
+        sleep(Duration::from_secs(600)).await;
+        let db = db.read().await;
+        for series in db.series_list.values().filter(|s| s.auto_fill) {
+            let report = analyze(series.clone(), collect_episodes(series, &db.rss_list).await);
+            for episode in report.missing {
+                let available = report
+                    .episodes
+                    .iter()
+                    .any(|e| e.episode == episode && pick_source(&e.sources).is_some());
+                if !available {
+                    continue;
+                }
+                match fetch_episode(series, episode, &db, &state, &config).await {
+                    Ok(source) => info!(
+                        "Series {}: downloading episode {} from {}",
+                        series.name, episode, source.title
+                    ),
+                    Err(e) => error!(
+                        "Series {}: fetch episode {} error: {}",
+                        series.name, episode, e
+                    ),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn source(item_id: usize, status: RssItemStatus) -> EpisodeSource {
+        EpisodeSource {
+            rss_id: 1,
+            item_id,
+            title: String::new(),
+            status,
+            group: None,
+            resolution: None,
+        }
+    }
+
+    #[test]
+    fn test_analyze() {
+        let series = Series {
+            id: 1,
+            name: "Frieren".to_owned(),
+            rss_ids: vec![1],
+            keywords: Vec::new(),
+            season: None,
+            total_episodes: None,
+            auto_fill: false,
+            owner: None,
+        };
+        let episodes = BTreeMap::from([
+            (1, vec![source(0, RssItemStatus::Downloaded)]),
+            (
+                2,
+                vec![
+                    source(1, RssItemStatus::Downloaded),
+                    source(2, RssItemStatus::Downloaded),
+                ],
+            ),
+            (4, vec![source(3, RssItemStatus::Read)]),
+            (5, vec![source(4, RssItemStatus::Downloading)]),
+        ]);
+        let report = analyze(series.clone(), episodes.clone());
+        assert_eq!(report.missing, vec![3, 4]);
+        assert_eq!(report.duplicates, vec![2]);
+        assert_eq!(report.episodes.len(), 5);
+
+        let report = analyze(
+            Series {
+                total_episodes: Some(6),
+                ..series
+            },
+            episodes,
+        );
+        assert_eq!(report.missing, vec![3, 4, 6]);
+    }
+}
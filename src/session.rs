@@ -0,0 +1,212 @@
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::utils::{rand_str, sha256};
+
+/// 登录会话设置，时间单位为秒，为 0 时不限制
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct SessionOptions {
+    /// 超过该时间没有请求的会话失效
+    pub idle_timeout: u64,
+    /// 会话从登录起的最长有效时间
+    pub max_age: u64,
+    /// 将会话保存到数据库，重启后无需重新登录
+    pub persist: bool,
+}
+
+impl Default for SessionOptions {
+    fn default() -> Self {
+        Self {
+            idle_timeout: 7 * 24 * 3600,
+            max_age: 30 * 24 * 3600,
+            persist: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct Session {
+    /// 公开的会话标识，用于列出和注销会话，不能用于认证
+    pub id: String,
+    /// 登录的用户，用户被删除或修改密码后会话失效
+    #[serde(default)]
+    pub username: String,
+    pub created_at: u64,
+    pub last_seen: u64,
+    pub user_agent: Option<String>,
+    pub address: Option<String>,
+}
+
+impl Session {
+    fn is_expired(&self, options: &SessionOptions, now: u64) -> bool {
+        let exceeds = |since: u64, limit: u64| limit > 0 && now.saturating_sub(since) > limit;
+        exceeds(self.last_seen, options.idle_timeout) || exceeds(self.created_at, options.max_age)
+    }
+}
+
+/// 所有登录会话，以令牌的 SHA-256 为键，保存的数据中不包含令牌本身
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Sessions {
+    sessions: HashMap<String, Session>,
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl Sessions {
+    /// 创建会话，返回令牌
+    pub fn create(
+        &mut self,
+        username: &str,
+        user_agent: Option<String>,
+        address: Option<String>,
+    ) -> String {
+        let token = rand_str(32);
+        let now = now();
+        self.sessions.insert(
+            sha256(&token),
+            Session {
+                id: rand_str(16),
+                username: username.to_owned(),
+                created_at: now,
+                last_seen: now,
+                user_agent,
+                address,
+            },
+        );
+        token
+    }
+
+    /// 校验令牌并更新最后活动时间，会话已过期时将其删除
+    pub fn validate(&mut self, token: &str, options: &SessionOptions) -> Option<&Session> {
+        let key = sha256(token);
+        let now = now();
+        if self.sessions.get(&key)?.is_expired(options, now) {
+            self.sessions.remove(&key);
+            return None;
+        }
+        let session = self.sessions.get_mut(&key)?;
+        session.last_seen = now;
+        Some(session)
+    }
+
+    pub fn get(&self, token: &str) -> Option<&Session> {
+        self.sessions.get(&sha256(token))
+    }
+
+    pub fn remove(&mut self, token: &str) -> bool {
+        self.sessions.remove(&sha256(token)).is_some()
+    }
+
+    /// 注销用户的指定会话
+    pub fn remove_by_id(&mut self, username: &str, id: &str) -> bool {
+        let len = self.sessions.len();
+        self.sessions
+            .retain(|_, session| session.username != username || session.id != id);
+        self.sessions.len() != len
+    }
+
+    /// 注销用户的所有会话
+    pub fn remove_user(&mut self, username: &str) {
+        self.sessions
+            .retain(|_, session| session.username != username);
+    }
+
+    /// 注销令牌所属用户的其他会话
+    pub fn remove_others(&mut self, token: &str) {
+        let key = sha256(token);
+        let Some(username) = self.sessions.get(&key).map(|s| s.username.clone()) else {
+            return;
+        };
+        self.sessions
+            .retain(|k, session| *k == key || session.username != username);
+    }
+
+    /// 只保留 `f` 返回 `true` 的用户的会话
+    pub fn retain_users(&mut self, f: impl Fn(&str) -> bool) {
+        self.sessions.retain(|_, session| f(&session.username));
+    }
+
+    /// 删除所有过期的会话
+    pub fn prune(&mut self, options: &SessionOptions) {
+        let now = now();
+        self.sessions
+            .retain(|_, session| !session.is_expired(options, now));
+    }
+
+    /// 用户的会话，按最后活动时间从新到旧排列
+    pub fn list(&self, username: &str) -> Vec<Session> {
+        let mut list: Vec<Session> = self
+            .sessions
+            .values()
+            .filter(|session| session.username == username)
+            .cloned()
+            .collect();
+        list.sort_by_key(|session| std::cmp::Reverse(session.last_seen));
+        list
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sessions() {
+        let options = SessionOptions {
+            idle_timeout: 60,
+            max_age: 3600,
+            persist: true,
+        };
+        let mut sessions = Sessions::default();
+        let desktop = sessions.create("admin", Some("desktop".to_owned()), None);
+        let phone = sessions.create("admin", Some("phone".to_owned()), None);
+        assert!(sessions.validate(&desktop, &options).is_some());
+        assert!(sessions.validate(&phone, &options).is_some());
+        assert!(sessions.validate("invalid", &options).is_none());
+        // 保存的数据中不包含令牌
+        let saved = serde_json::to_string(&sessions).unwrap();
+        assert!(!saved.contains(&desktop));
+
+        let now = now();
+        sessions
+            .sessions
+            .get_mut(&sha256(&phone))
+            .unwrap()
+            .last_seen = now - 120;
+        assert!(sessions.validate(&phone, &options).is_none());
+        assert_eq!(sessions.list("admin").len(), 1);
+        sessions
+            .sessions
+            .get_mut(&sha256(&desktop))
+            .unwrap()
+            .created_at = now - 7200;
+        sessions.prune(&options);
+        assert!(sessions.list("admin").is_empty());
+
+        let a = sessions.create("admin", None, None);
+        let b = sessions.create("admin", None, None);
+        let c = sessions.create("alice", None, None);
+        let id = sessions.get(&b).unwrap().id.clone();
+        assert!(!sessions.remove_by_id("alice", &id));
+        assert!(sessions.remove_by_id("admin", &id));
+        sessions.create("admin", None, None);
+        sessions.remove_others(&a);
+        assert_eq!(sessions.list("admin").len(), 1);
+        assert!(sessions.get(&c).is_some());
+        sessions.remove_user("alice");
+        assert!(sessions.get(&c).is_none());
+        assert!(sessions.remove(&a));
+        assert!(!sessions.remove(&a));
+    }
+}
@@ -1,36 +1,112 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 use std::sync::Weak;
+use std::time::Instant;
 use std::{fs::read_to_string, path::PathBuf, sync::Arc, time::Duration};
 use tokio::sync::{RwLockReadGuard, RwLockWriteGuard};
-use tokio::{fs::write, sync::RwLock, time::sleep};
-use tracing::info;
+use tokio::{sync::RwLock, time::sleep};
+use tracing::{error, warn};
 use ts_rs::TS;
 
-use crate::downloader::Downloader;
+use crate::api_key::ApiKeys;
+use crate::backup::{self, BackupOptions};
+use crate::config::{self, Overrides};
+use crate::downloader::{rqbit::DownloadTask, Downloader};
+use crate::metadata::{MetadataOptions, MetadataQueue};
 use crate::rss::Rss;
+use crate::search::SearchIndex;
+use crate::series::Series;
+use crate::session::{SessionOptions, Sessions};
+use crate::store::Store;
+use crate::tls::TlsOptions;
+use crate::user::{Role, User};
 
 //use crate::{download::DownloadTask, rss::Rss};
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[ts(export)]
 pub struct Config {
+    /// 监听地址，多个地址用逗号分隔，`unix:<path>` 为 Unix 域套接字
     pub bind_address: String,
+    /// 登录密码的 Argon2id 哈希，旧版本的 SHA-256 哈希会在登录时升级
     pub password: String,
+    /// 内置账户的用户名，该账户始终为管理员
     pub username: String,
     pub token: Option<String>,
     pub db_path: String,
     pub session_path: String,
     pub torrent_options: TorrentOptions,
     pub output_path: String,
+    #[serde(default)]
+    pub metadata_options: MetadataOptions,
+    /// 访问已完成下载订阅源所需的密钥
+    #[serde(default)]
+    pub feed_key: Option<String>,
+    /// 对外访问的地址，用于生成订阅源中的文件链接，为空时使用请求的 Host
+    #[serde(default)]
+    pub public_url: Option<String>,
+    /// 订阅分类，以分类名为键
+    #[serde(default)]
+    pub categories: HashMap<String, Category>,
+    #[serde(default)]
+    pub backup_options: BackupOptions,
+    /// Unix 域套接字文件的权限，八进制，例如 `660`
+    #[serde(default)]
+    pub unix_socket_mode: Option<String>,
+    /// HTTPS 设置，为空时只使用 HTTP
+    #[serde(default)]
+    pub tls: Option<TlsOptions>,
+    /// 从该目录提供网页界面而不是使用内嵌的文件，用于前端开发
+    #[serde(default)]
+    pub webui_dir: Option<String>,
+    #[serde(default)]
+    pub session_options: SessionOptions,
+    /// 内置账户以外的用户，以用户名为键
+    #[serde(default)]
+    pub users: BTreeMap<String, User>,
 }
 
 impl Config {
+    /// 读取 JSON 或 TOML 格式的配置文件，文件中没有的字段使用默认值
     pub fn from_path(path: PathBuf) -> Result<Self> {
-        let content = read_to_string(path)?;
-        let res = serde_json::from_str(&content)?;
-        Ok(res)
+        let content = read_to_string(&path)?;
+        let mut value = serde_json::to_value(Self::default())?;
+        config::merge(&mut value, config::parse(&path, &content)?);
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// 读取配置文件，文件损坏时依次尝试备份，并用可用的备份替换损坏的文件
+    pub fn load(path: &Path) -> Result<Self> {
+        let err = match Self::from_path(path.into()) {
+            Ok(config) => return Ok(config),
+            Err(e) => e,
+        };
+        error!("Failed to load config {}: {:#}", path.display(), err);
+        for backup in backup::backups(path) {
+            match Self::from_path(backup.clone()) {
+                Ok(config) => {
+                    warn!("Recovered config from {}", backup.display());
+                    if path.exists() {
+                        backup::set_aside(path)?;
+                    }
+                    config.save(path)?;
+                    return Ok(config);
+                }
+                Err(e) => error!("Failed to load backup {}: {:#}", backup.display(), e),
+            }
+        }
+        Err(err.context(format!("Load config {}", path.display())))
+    }
+
+    /// 原子地写入配置文件，内容有变化时保留旧文件作为备份
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let data = config::format(path, self)?;
+        // 先记录再写入，避免监视任务在两者之间读到文件
+        config::record_written(data.as_bytes());
+        backup::write_with_backup(path, data.as_bytes(), self.backup_options.count)
+            .with_context(|| format!("Save config {}", path.display()))
     }
 
     pub fn update_password(self, new_pw: String) -> Self {
@@ -40,12 +116,47 @@ impl Config {
         }
     }
 
-    pub fn update_bind_addr(self, new_ba: String) -> Self {
-        Self {
-            bind_address: new_ba,
-            ..self
+    /// 按用户名查找账户，返回密码哈希和角色
+    pub fn account(&self, username: &str) -> Option<(&str, Role)> {
+        if username == self.username {
+            return Some((&self.password, Role::Admin));
+        }
+        self.users
+            .get(username)
+            .map(|user| (user.password.as_str(), user.role))
+    }
+
+    /// 修改账户的密码哈希，账户不存在时返回 `false`
+    pub fn set_password(&mut self, username: &str, hash: String) -> bool {
+        if username == self.username {
+            self.password = hash;
+            return true;
+        }
+        match self.users.get_mut(username) {
+            Some(user) => {
+                user.password = hash;
+                true
+            }
+            None => false,
         }
     }
+
+    pub fn category(&self, name: Option<&str>) -> Option<&Category> {
+        self.categories.get(name?)
+    }
+
+    /// 分类使用的 tracker，分类的 tracker 会追加在全局 tracker 之后
+    pub fn trackers(&self, category: Option<&str>) -> Vec<String> {
+        let mut trackers = self.torrent_options.trackers.clone();
+        if let Some(category) = self.category(category) {
+            for tracker in category.trackers.iter() {
+                if !trackers.contains(tracker) {
+                    trackers.push(tracker.clone());
+                }
+            }
+        }
+        trackers
+    }
 }
 
 impl Default for Config {
@@ -55,20 +166,35 @@ impl Default for Config {
             username: "admin".to_owned(),
             password: "".to_owned(),
             token: None,
-            db_path: "./db.bin".to_owned(),
+            db_path: "./nekodl.db".to_owned(),
             session_path: "./session".to_owned(),
             torrent_options: TorrentOptions {
                 trackers: Vec::new(),
             },
             output_path: "./downloads".to_owned(),
+            metadata_options: MetadataOptions::default(),
+            feed_key: None,
+            public_url: None,
+            categories: HashMap::new(),
+            backup_options: BackupOptions::default(),
+            unix_socket_mode: None,
+            tls: None,
+            webui_dir: None,
+            session_options: SessionOptions::default(),
+            users: BTreeMap::new(),
         }
     }
 }
 
 #[derive(Clone)]
 pub struct State {
-    pub token: Option<String>,
+    pub sessions: Sessions,
+    pub api_keys: ApiKeys,
     pub downloader: Arc<dyn Downloader>,
+    pub metadata_queue: MetadataQueue,
+    pub search_index: Arc<RwLock<SearchIndex>>,
+    /// 手动添加的下载任务，以任务 ID 为键
+    pub download_tasks: HashMap<usize, DownloadTask>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -76,6 +202,34 @@ pub struct TorrentOptions {
     pub trackers: Vec<String>,
 }
 
+/// 订阅分类，同一分类的订阅共享下载目录、做种策略、tracker 和优先级
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+pub struct Category {
+    /// 下载目录，为空时使用全局的 `output_path`
+    #[serde(default)]
+    pub output_path: Option<String>,
+    #[serde(default)]
+    pub seeding: SeedingPolicy,
+    /// 额外的 tracker
+    #[serde(default)]
+    pub trackers: Vec<String>,
+    /// 优先级，数值越大越先获取元数据
+    #[serde(default)]
+    pub priority: i32,
+}
+
+/// 下载完成后的做种策略，满足任一条件即停止做种，都为空时一直做种
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+pub struct SeedingPolicy {
+    /// 分享率上限
+    pub ratio_limit: Option<f64>,
+    /// 做种时间上限，单位为秒
+    pub seed_time: Option<u64>,
+}
+
+/// 可共享的读写锁。
+/// 不实现 `Serialize`，保存或返回数据时先通过 `read().await` 或 `clone_inner` 取得快照，
+/// 避免在同步的序列化过程中等待锁。
 #[derive(Debug, Clone)]
 pub struct SerdeLockLayer<T> {
     inner: Arc<RwLock<T>>,
@@ -142,73 +296,121 @@ impl<T> From<T> for SerdeLockLayer<T> {
     }
 }
 
-impl<T> Serialize for SerdeLockLayer<T>
-where
-    T: Serialize,
-{
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        loop {
-            match self.inner.try_write() {
-                Ok(guard) => {
-                    return serializer.serialize_some(&(*guard));
-                }
-                Err(_) => {}
-            }
-        }
-    }
-}
-
-impl<'de, T> Deserialize<'de> for SerdeLockLayer<T>
-where
-    T: Deserialize<'de>,
-{
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        match T::deserialize(deserializer) {
-            Ok(t) => Ok(SerdeLockLayer::new(t)),
-            Err(e) => Err(e),
-        }
-    }
-}
-
-#[derive(Serialize, Deserialize)]
+/// 内存中的数据，由 `Store` 负责持久化
 pub struct DataBase {
     pub rss_list: HashMap<usize, SerdeLockLayer<Rss>>,
     pub rss_id_index: usize,
+    pub series_list: HashMap<usize, Series>,
+    pub series_id_index: usize,
     //pub download_task_list: Vec<DownloadTask>,
 }
 
-impl DataBase {
-    pub async fn save(&self, path: &str) -> Result<()> {
-        info!("save db to {}", path);
-        let data = bincode::serialize(self)?;
-        let path = PathBuf::from(path);
-        tokio::fs::write(path, data).await?;
-        Ok(())
+/// 去除环境变量和命令行参数的覆盖后写入配置文件，内容与 `saved` 相同时不写入。
+/// 会读写配置文件，需要在阻塞线程中调用。
+fn save_config(
+    config: &Config,
+    path: &Path,
+    overrides: &Overrides,
+    saved: &mut Option<serde_json::Value>,
+) -> Result<()> {
+    // 环境变量和命令行参数的覆盖不写入配置文件
+    let file = Config::from_path(path.to_owned()).unwrap_or_default();
+    let config = overrides
+        .strip(config, &file)
+        .context("Failed to prepare config for saving")?;
+    let value = serde_json::to_value(&config).ok();
+    if value != *saved {
+        config.save(path)?;
+        *saved = value;
     }
+    Ok(())
 }
 
 pub async fn data_save_task(
     db: Arc<RwLock<DataBase>>,
+    store: Store,
     config: Arc<RwLock<Config>>,
-    config_path: String,
+    state: Arc<RwLock<State>>,
+    config_path: PathBuf,
+    overrides: Overrides,
 ) {
+    let mut last_backup = Instant::now();
+    // 上次写入的会话，启动后第一次总是写入，不保存会话时删除已保存的会话
+    let mut saved_sessions = None;
+    // 上次写入的 API 密钥，密钥的最后使用时间变化时也需要写入
+    let mut saved_api_keys = None;
+    // 上次写入的配置，配置没有变化时不写入，避免覆盖外部对配置文件的修改和格式
+    let mut saved_config = {
+        let path = config_path.clone();
+        tokio::task::spawn_blocking(move || {
+            Config::from_path(path)
+                .ok()
+                .and_then(|config| serde_json::to_value(config).ok())
+        })
+        .await
+        .ok()
+        .flatten()
+    };
+    let overrides = Arc::new(overrides);
     loop {
         sleep(Duration::from_secs(60)).await;
+        // 保存失败时只记录错误，下一次继续尝试，各部分的保存互不影响
         let config_dup = { config.read().await.clone() };
-        write(
-            &config_path,
-            serde_json::to_string_pretty(&config_dup)
-                .unwrap()
-                .as_bytes(),
-        )
+        let (path, overrides) = (config_path.clone(), overrides.clone());
+        let mut saved = saved_config.take();
+        match tokio::task::spawn_blocking(move || {
+            let res = save_config(&config_dup, &path, &overrides, &mut saved);
+            (saved, res)
+        })
         .await
-        .unwrap();
-        db.write().await.save(&config_dup.db_path).await.unwrap();
+        {
+            Ok((saved, res)) => {
+                saved_config = saved;
+                if let Err(e) = res {
+                    error!("{:#}", e);
+                }
+            }
+            Err(e) => error!("Config save task failed: {}", e),
+        }
+        if let Err(e) = store.save(&db).await {
+            error!("Failed to save database: {:#}", e);
+        }
+        let options = config.read().await.session_options.clone();
+        let sessions = {
+            let mut state = state.write().await;
+            state.sessions.prune(&options);
+            options
+                .persist
+                .then(|| serde_json::to_string(&state.sessions))
+                .transpose()
+        };
+        match sessions {
+            Ok(sessions) if saved_sessions.as_ref() != Some(&sessions) => {
+                match store.save_sessions(sessions.clone()).await {
+                    Ok(()) => saved_sessions = Some(sessions),
+                    Err(e) => error!("Failed to save sessions: {:#}", e),
+                }
+            }
+            Ok(_) => {}
+            Err(e) => error!("Failed to serialize sessions: {:#}", e),
+        }
+        let api_keys = serde_json::to_string(&state.read().await.api_keys);
+        match api_keys {
+            Ok(api_keys) if saved_api_keys.as_ref() != Some(&api_keys) => {
+                match store.save_api_keys(api_keys.clone()).await {
+                    Ok(()) => saved_api_keys = Some(api_keys),
+                    Err(e) => error!("Failed to save API keys: {:#}", e),
+                }
+            }
+            Ok(_) => {}
+            Err(e) => error!("Failed to serialize API keys: {:#}", e),
+        }
+        let options = config.read().await.backup_options.clone();
+        if options.count > 0 && last_backup.elapsed() >= Duration::from_secs(options.interval) {
+            match store.backup(options.count).await {
+                Ok(()) => last_backup = Instant::now(),
+                Err(e) => error!("Failed to back up database: {:#}", e),
+            }
+        }
     }
 }
@@ -1,3 +1,192 @@
+use std::{
+    path::{Component, Path},
+    sync::Arc,
+};
+
+use salvo::{
+    fs::NamedFile,
+    http::header::{
+        HeaderName, HeaderValue, ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_TYPE,
+        ETAG, IF_NONE_MATCH, VARY,
+    },
+    prelude::*,
+};
+use tokio::sync::RwLock;
+
+use crate::{state::Config, utils::FromDepot};
+
 #[derive(rust_embed::Embed)]
 #[folder = "webui/dist/"]
 struct Asset;
+
+/// 带内容哈希的构建产物，可以长期缓存
+const IMMUTABLE_PREFIX: &str = "static/";
+const INDEX: &str = "index.html";
+
+/// 预压缩文件的编码和扩展名，按优先级排列
+const ENCODINGS: [(&str, &str); 2] = [("br", ".br"), ("gzip", ".gz")];
+
+/// 将请求路径解析为文件路径。文件不存在且路径没有扩展名时视为前端路由，返回 `index.html`。
+/// 路径中包含 `..` 等非普通组件时返回 `None`。
+fn resolve(path: &str, exists: impl Fn(&str) -> bool) -> Option<String> {
+    let path = path.trim_start_matches('/');
+    if path.is_empty() || path.ends_with('/') {
+        return Some(INDEX.to_owned());
+    }
+    if !Path::new(path)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return None;
+    }
+    if exists(path) {
+        return Some(path.to_owned());
+    }
+    let name = path.rsplit('/').next().unwrap_or(path);
+    (!name.contains('.')).then(|| INDEX.to_owned())
+}
+
+/// 客户端接受的预压缩编码，`q=0` 表示不接受
+fn accepted_encodings(accept: &str) -> Vec<(&'static str, &'static str)> {
+    let accepted: Vec<&str> = accept
+        .split(',')
+        .filter_map(|part| {
+            let mut params = part.split(';').map(str::trim);
+            let name = params.next()?;
+            let refused = params.any(|p| {
+                p.strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.0)
+            });
+            (!refused).then_some(name)
+        })
+        .collect();
+    ENCODINGS
+        .into_iter()
+        .filter(|(name, _)| accepted.iter().any(|a| a == name || *a == "*"))
+        .collect()
+}
+
+fn cache_control(path: &str) -> &'static str {
+    if path.starts_with(IMMUTABLE_PREFIX) {
+        "public, max-age=31536000, immutable"
+    } else {
+        "no-cache"
+    }
+}
+
+fn not_modified(req: &Request, etag: &str) -> bool {
+    req.headers()
+        .get(IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').map(str::trim).any(|t| t == etag || t == "*"))
+}
+
+fn set_header(res: &mut Response, name: HeaderName, value: &str) {
+    if let Ok(value) = HeaderValue::from_str(value) {
+        res.headers_mut().insert(name, value);
+    }
+}
+
+/// 提供网页界面，配置了 `webui_dir` 时从磁盘读取，否则使用编译时嵌入的文件
+#[handler]
+pub async fn webui(req: &mut Request, depot: &mut Depot, res: &mut Response) {
+    let path = req.uri().path().trim_start_matches('/').to_owned();
+    // 未匹配的 API 请求不回退到前端页面
+    if path == "api" || path.starts_with("api/") {
+        res.status_code(StatusCode::NOT_FOUND);
+        return;
+    }
+    let webui_dir = match Arc::<RwLock<Config>>::from_depot(depot) {
+        Ok(config) => config.read().await.webui_dir.clone(),
+        Err(_) => {
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+            return;
+        }
+    };
+    match webui_dir {
+        Some(dir) => serve_dir(Path::new(&dir), &path, req, res).await,
+        None => serve_embedded(&path, req, res),
+    }
+}
+
+/// 开发时从磁盘读取文件，不使用缓存
+async fn serve_dir(dir: &Path, path: &str, req: &mut Request, res: &mut Response) {
+    let Some(file) = resolve(path, |p| dir.join(p).is_file()) else {
+        res.status_code(StatusCode::NOT_FOUND);
+        return;
+    };
+    NamedFile::builder(dir.join(file))
+        .send(req.headers(), res)
+        .await;
+    set_header(res, CACHE_CONTROL, "no-cache");
+}
+
+fn serve_embedded(path: &str, req: &Request, res: &mut Response) {
+    let Some(path) = resolve(path, |p| Asset::get(p).is_some()) else {
+        res.status_code(StatusCode::NOT_FOUND);
+        return;
+    };
+    let Some(file) = Asset::get(&path) else {
+        res.status_code(StatusCode::NOT_FOUND);
+        return;
+    };
+    let accept = req
+        .headers()
+        .get(ACCEPT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let compressed = accepted_encodings(accept)
+        .into_iter()
+        .find_map(|(name, ext)| Some((name, Asset::get(&format!("{}{}", path, ext))?)));
+    let (encoding, content) = match compressed {
+        Some((name, content)) => (Some(name), content),
+        None => (None, file.clone()),
+    };
+    let etag = format!(
+        "\"{}\"",
+        content
+            .metadata
+            .sha256_hash()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    );
+
+    set_header(res, CONTENT_TYPE, file.metadata.mimetype());
+    set_header(res, CACHE_CONTROL, cache_control(&path));
+    set_header(res, VARY, "Accept-Encoding");
+    set_header(res, ETAG, &etag);
+    if not_modified(req, &etag) {
+        res.status_code(StatusCode::NOT_MODIFIED);
+        return;
+    }
+    if let Some(encoding) = encoding {
+        set_header(res, CONTENT_ENCODING, encoding);
+    }
+    res.body(content.data.into_owned());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let exists = |p: &str| ["index.html", "static/js/app.1234.js"].contains(&p);
+        assert_eq!(resolve("/", exists).as_deref(), Some("index.html"));
+        assert_eq!(
+            resolve("/static/js/app.1234.js", exists).as_deref(),
+            Some("static/js/app.1234.js")
+        );
+        assert_eq!(resolve("/rss/3", exists).as_deref(), Some("index.html"));
+        assert_eq!(resolve("/static/js/missing.js", exists), None);
+        assert_eq!(resolve("/../config.json", exists), None);
+        assert_eq!(
+            accepted_encodings("gzip, deflate, br;q=0"),
+            vec![("gzip", ".gz")]
+        );
+        assert_eq!(accepted_encodings("*").len(), 2);
+        assert!(accepted_encodings("identity").is_empty());
+    }
+}
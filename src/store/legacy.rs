@@ -0,0 +1,244 @@
+//! 旧版本 `db.bin`（bincode 格式）及其 JSON 附属文件的数据结构，只用于导入，不要修改
+
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::{
+    rss::{self, Rss, RssItem},
+    series::Series,
+    state::{DataBase, SerdeLockLayer},
+};
+
+/// 旧版本的 `SerdeLockLayer` 序列化时通过 `serialize_some` 写入，带有 `Some` 标记
+type Layer<T> = Option<T>;
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct LegacyDataBase {
+    rss_list: HashMap<usize, Layer<LegacyRss>>,
+    rss_id_index: usize,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct LegacyRss {
+    id: usize,
+    url: String,
+    title: String,
+    description: String,
+    items: Vec<Layer<LegacyRssItem>>,
+    update_time: SystemTime,
+    update_interval: Duration,
+    status: LegacyRssStatus,
+    auto_download: bool,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct LegacyRssItem {
+    title: String,
+    link: String,
+    description: String,
+    status: LegacyRssItemStatus,
+    torrent: Option<LegacyItemTorrent>,
+    id: usize,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct LegacyItemTorrent {
+    files: Vec<LegacyTorrentFileInfo>,
+    update_time: SystemTime,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct LegacyTorrentFileInfo {
+    filename: String,
+    offset: u64,
+    length: u64,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+enum LegacyRssItemStatus {
+    Unread,
+    Read,
+    Downloading,
+    Downloaded,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+enum LegacyRssStatus {
+    Read,
+    Created,
+    Updated,
+    Error(String),
+}
+
+impl From<LegacyRssItem> for RssItem {
+    fn from(item: LegacyRssItem) -> Self {
+        RssItem {
+            title: item.title,
+            link: item.link,
+            description: item.description,
+            status: match item.status {
+                LegacyRssItemStatus::Unread => rss::RssItemStatus::Unread,
+                LegacyRssItemStatus::Read => rss::RssItemStatus::Read,
+                // 导入时没有正在运行的下载任务
+                LegacyRssItemStatus::Downloading => rss::RssItemStatus::Unread,
+                LegacyRssItemStatus::Downloaded => rss::RssItemStatus::Downloaded,
+            },
+            torrent: item.torrent.map(|torrent| rss::ItemTorrent {
+                files: torrent
+                    .files
+                    .into_iter()
+                    .map(|f| rss::TorrentFileInfo {
+                        filename: f.filename,
+                        offset: f.offset,
+                        length: f.length,
+                    })
+                    .collect(),
+                update_time: torrent.update_time,
+            }),
+            metadata: Default::default(),
+            info_hash: None,
+            downloaded_time: None,
+            add_time: None,
+            guid: None,
+            pub_date: None,
+            categories: Vec::new(),
+            author: None,
+            size: None,
+            seeders: None,
+            id: item.id,
+            download_handle: None,
+        }
+    }
+}
+
+impl From<LegacyRss> for Rss {
+    fn from(rss: LegacyRss) -> Self {
+        Rss {
+            items: rss
+                .items
+                .into_iter()
+                .flatten()
+                .map(|item| SerdeLockLayer::new(item.into()))
+                .collect(),
+            update_time: rss.update_time,
+            update_interval: rss.update_interval,
+            status: match rss.status {
+                LegacyRssStatus::Read => rss::RssStatus::Read,
+                LegacyRssStatus::Created => rss::RssStatus::Created,
+                LegacyRssStatus::Updated => rss::RssStatus::Updated,
+                LegacyRssStatus::Error(e) => rss::RssStatus::Error(e),
+            },
+            auto_download: rss.auto_download,
+            ..Rss::new(rss.id, rss.url, rss.title, rss.description)
+        }
+    }
+}
+
+/// 解析旧版本的 `db.bin`
+pub fn parse(data: &[u8]) -> Result<DataBase> {
+    let legacy: LegacyDataBase = bincode::deserialize(data)?;
+    Ok(DataBase {
+        rss_list: legacy
+            .rss_list
+            .into_iter()
+            .filter_map(|(id, rss)| Some((id, SerdeLockLayer::new(rss?.into()))))
+            .collect(),
+        rss_id_index: legacy.rss_id_index,
+        series_list: HashMap::new(),
+        series_id_index: 0,
+    })
+}
+
+/// 附属文件中的订阅，条目随订阅一起保存
+#[derive(Deserialize)]
+struct SideRss {
+    #[serde(flatten)]
+    rss: Rss,
+    items: Vec<RssItem>,
+}
+
+/// `db.bin` 的 JSON 附属文件 `<db_path>.json`，包含 `db.bin` 中没有的字段
+#[derive(Deserialize)]
+struct SideDataBase {
+    rss_list: HashMap<usize, SideRss>,
+    rss_id_index: usize,
+    #[serde(default)]
+    series_list: HashMap<usize, Series>,
+    #[serde(default)]
+    series_id_index: usize,
+}
+
+/// 解析 `db.bin` 的 JSON 附属文件
+pub fn parse_side(data: &[u8]) -> Result<DataBase> {
+    let side: SideDataBase = serde_json::from_slice(data)?;
+    Ok(DataBase {
+        rss_list: side
+            .rss_list
+            .into_iter()
+            .map(|(id, side)| {
+                let mut rss = side.rss;
+                rss.items = side
+                    .items
+                    .into_iter()
+                    .map(|mut item| {
+                        // 导入时没有正在运行的下载任务
+                        if item.status == rss::RssItemStatus::Downloading {
+                            item.status = rss::RssItemStatus::Unread;
+                        }
+                        SerdeLockLayer::new(item)
+                    })
+                    .collect();
+                (id, SerdeLockLayer::new(rss))
+            })
+            .collect(),
+        rss_id_index: side.rss_id_index,
+        series_list: side.series_list,
+        series_id_index: side.series_id_index,
+    })
+}
+
+/// 按旧版本的格式生成一个包含一个订阅和两个条目的 `db.bin`
+#[cfg(test)]
+pub fn sample() -> Vec<u8> {
+    let item = |id: usize, status| {
+        Some(LegacyRssItem {
+            title: format!("[ANi] Frieren - {:02}", id + 1),
+            link: format!("https://example.com/{}.torrent", id),
+            description: String::new(),
+            status,
+            torrent: None,
+            id,
+        })
+    };
+    let rss = LegacyRss {
+        id: 1,
+        url: "https://mikanani.me/RSS/Bangumi?bangumiId=3141".to_owned(),
+        title: "Frieren".to_owned(),
+        description: String::new(),
+        items: vec![
+            item(0, LegacyRssItemStatus::Downloaded),
+            item(1, LegacyRssItemStatus::Unread),
+        ],
+        update_time: SystemTime::UNIX_EPOCH,
+        update_interval: Duration::from_secs(3600),
+        status: LegacyRssStatus::Updated,
+        auto_download: true,
+    };
+    bincode::serialize(&LegacyDataBase {
+        rss_list: HashMap::from([(1, Some(rss))]),
+        rss_id_index: 1,
+    })
+    .unwrap()
+}
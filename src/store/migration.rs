@@ -0,0 +1,107 @@
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+
+/// 当前的数据库结构版本。
+/// 0 为已发布版本使用的 `db.bin`，由 `Store::open_or_import` 导入为版本 1 的 SQLite 数据库，
+/// 之后的结构变化在 `MIGRATIONS` 中逐个版本追加。
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// 将数据库从上一个版本升级到 `version` 的迁移步骤，返回每一处修改的说明
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    apply: fn(&Transaction) -> Result<Vec<String>>,
+}
+
+pub const MIGRATIONS: &[Migration] = &[];
+
+pub struct MigrationStep {
+    pub version: u32,
+    pub description: &'static str,
+    pub changes: Vec<String>,
+}
+
+pub struct MigrationReport {
+    pub from: u32,
+    pub to: u32,
+    pub steps: Vec<MigrationStep>,
+}
+
+/// 读取数据库结构版本，没有版本号的是刚创建的数据库
+pub fn schema_version(conn: &Connection) -> Result<u32> {
+    let version: Option<String> = conn
+        .query_row(
+            "SELECT value FROM meta WHERE key = 'schema_version'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match version {
+        Some(version) => Ok(version.parse()?),
+        None => Ok(SCHEMA_VERSION),
+    }
+}
+
+/// 创建缺少的表并依次执行需要的迁移步骤，`dry_run` 时回滚所有修改，只返回会发生的变化
+pub fn migrate(conn: &mut Connection, dry_run: bool) -> Result<MigrationReport> {
+    let tx = conn.transaction()?;
+    tx.execute_batch(super::SCHEMA)?;
+    let from = schema_version(&tx)?;
+    if from > SCHEMA_VERSION {
+        return Err(anyhow!(
+            "Database schema version {} is newer than supported version {}",
+            from,
+            SCHEMA_VERSION
+        ));
+    }
+    let mut steps = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.version > from) {
+        steps.push(MigrationStep {
+            version: migration.version,
+            description: migration.description,
+            changes: (migration.apply)(&tx)?,
+        });
+    }
+    tx.execute(
+        "INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', ?1)",
+        params![SCHEMA_VERSION.to_string()],
+    )?;
+    if !dry_run {
+        tx.commit()?;
+    }
+    Ok(MigrationReport {
+        from,
+        to: SCHEMA_VERSION,
+        steps,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_newer_version() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn, false).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        assert!(migrate(&mut conn, false).unwrap().steps.is_empty());
+        conn.execute(
+            "UPDATE meta SET value = ?1 WHERE key = 'schema_version'",
+            params![(SCHEMA_VERSION + 1).to_string()],
+        )
+        .unwrap();
+        assert!(migrate(&mut conn, false).is_err());
+    }
+
+    #[test]
+    fn test_dry_run_creates_nothing() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let report = migrate(&mut conn, true).unwrap();
+        assert_eq!(report.from, SCHEMA_VERSION);
+        let tables: i64 = conn
+            .query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(tables, 0);
+    }
+}
@@ -0,0 +1,788 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fs::{self, File},
+    hash::{Hash, Hasher},
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::de::DeserializeOwned;
+use tokio::sync::{Mutex as AsyncMutex, RwLock};
+use tracing::{error, info, warn};
+
+use crate::{
+    api_key::ApiKeys,
+    backup,
+    downloader::rqbit::DownloadTask,
+    rss::{Rss, RssItem},
+    series::Series,
+    session::Sessions,
+    state::{DataBase, SerdeLockLayer},
+};
+
+mod legacy;
+mod migration;
+
+pub use migration::SCHEMA_VERSION;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+CREATE TABLE IF NOT EXISTS rss (id INTEGER PRIMARY KEY, data TEXT NOT NULL);
+CREATE TABLE IF NOT EXISTS rss_item (
+    rss_id INTEGER NOT NULL,
+    id INTEGER NOT NULL,
+    data TEXT NOT NULL,
+    PRIMARY KEY (rss_id, id)
+);
+CREATE TABLE IF NOT EXISTS series (id INTEGER PRIMARY KEY, data TEXT NOT NULL);
+";
+
+/// 登录会话在 meta 表中的键，不随数据库整体保存
+const SESSIONS_KEY: &str = "sessions";
+
+/// API 密钥在 meta 表中的键，不随数据库整体保存
+const API_KEYS_KEY: &str = "api_keys";
+
+/// 手动添加的下载任务在 meta 表中的键，不随数据库整体保存
+const DOWNLOAD_TASKS_KEY: &str = "download_tasks";
+
+/// SQLite 数据库文件的文件头
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum RecordKey {
+    Meta(&'static str),
+    Rss(usize),
+    Item(usize, usize),
+    Series(usize),
+}
+
+impl RecordKey {
+    fn rss_id(&self) -> Option<usize> {
+        match self {
+            Self::Rss(id) | Self::Item(id, _) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+/// 一次保存涉及的记录，只删除范围内已经不存在的记录
+struct Snapshot {
+    records: Vec<(RecordKey, String)>,
+    /// 为空时范围为整个数据库，否则为该订阅及其条目
+    rss_id: Option<usize>,
+}
+
+struct Inner {
+    conn: Connection,
+    /// 上次写入的每条记录的哈希值，内容没有变化的记录不会重复写入
+    written: HashMap<RecordKey, u64>,
+}
+
+/// 基于 SQLite 的数据存储，每个订阅、条目和剧集单独保存为一条 JSON 记录
+#[derive(Clone)]
+pub struct Store {
+    inner: Arc<Mutex<Inner>>,
+    /// 保存时从读取快照到写入完成一直持有，
+    /// 避免较旧的快照删除另一次保存在此期间写入的记录
+    saving: Arc<AsyncMutex<()>>,
+}
+
+fn hash(data: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn parse_rss(data: &str, items: Vec<SerdeLockLayer<RssItem>>) -> Result<Rss> {
+    let mut rss: Rss = serde_json::from_str(data)?;
+    rss.items = items;
+    Ok(rss)
+}
+
+/// 读取一个订阅及其条目的记录。
+/// 只在复制条目句柄时持有订阅的读锁，之后逐个等待条目的读锁，
+/// 每条记录各自一致，正在被修改的条目不会阻塞其他记录的读取。
+async fn snapshot_rss(
+    rss: &SerdeLockLayer<Rss>,
+    records: &mut Vec<(RecordKey, String)>,
+) -> Result<()> {
+    let (rss_id, items) = {
+        let rss = rss.read().await;
+        records.push((RecordKey::Rss(rss.id), serde_json::to_string(&*rss)?));
+        (rss.id, rss.items.clone())
+    };
+    for item in items.iter() {
+        let item = item.read().await;
+        records.push((
+            RecordKey::Item(rss_id, item.id),
+            serde_json::to_string(&*item)?,
+        ));
+    }
+    Ok(())
+}
+
+fn meta_records(db: &DataBase) -> Vec<(RecordKey, String)> {
+    vec![
+        (RecordKey::Meta("rss_id_index"), db.rss_id_index.to_string()),
+        (
+            RecordKey::Meta("series_id_index"),
+            db.series_id_index.to_string(),
+        ),
+    ]
+}
+
+fn write_record(tx: &Transaction, key: RecordKey, data: &str) -> rusqlite::Result<usize> {
+    match key {
+        RecordKey::Meta(name) => tx.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+            params![name, data],
+        ),
+        RecordKey::Rss(id) => tx.execute(
+            "INSERT OR REPLACE INTO rss (id, data) VALUES (?1, ?2)",
+            params![id as i64, data],
+        ),
+        RecordKey::Item(rss_id, id) => tx.execute(
+            "INSERT OR REPLACE INTO rss_item (rss_id, id, data) VALUES (?1, ?2, ?3)",
+            params![rss_id as i64, id as i64, data],
+        ),
+        RecordKey::Series(id) => tx.execute(
+            "INSERT OR REPLACE INTO series (id, data) VALUES (?1, ?2)",
+            params![id as i64, data],
+        ),
+    }
+}
+
+fn delete_record(tx: &Transaction, key: RecordKey) -> rusqlite::Result<usize> {
+    match key {
+        RecordKey::Meta(name) => tx.execute("DELETE FROM meta WHERE key = ?1", params![name]),
+        RecordKey::Rss(id) => tx.execute("DELETE FROM rss WHERE id = ?1", params![id as i64]),
+        RecordKey::Item(rss_id, id) => tx.execute(
+            "DELETE FROM rss_item WHERE rss_id = ?1 AND id = ?2",
+            params![rss_id as i64, id as i64],
+        ),
+        RecordKey::Series(id) => tx.execute("DELETE FROM series WHERE id = ?1", params![id as i64]),
+    }
+}
+
+impl Inner {
+    /// 在一个事务中写入有变化的记录，并删除范围内已经不存在的记录
+    fn apply(&mut self, snapshot: Snapshot) -> Result<usize> {
+        let in_scope = |key: &RecordKey| match snapshot.rss_id {
+            None => true,
+            Some(rss_id) => key.rss_id() == Some(rss_id),
+        };
+        let mut changed = Vec::new();
+        let mut present = HashMap::new();
+        for (key, data) in snapshot.records.iter() {
+            let hash = hash(data);
+            present.insert(*key, hash);
+            if self.written.get(key) != Some(&hash) {
+                changed.push((*key, data.as_str()));
+            }
+        }
+        let removed: Vec<RecordKey> = self
+            .written
+            .keys()
+            .filter(|key| in_scope(key) && !present.contains_key(key))
+            .copied()
+            .collect();
+        if changed.is_empty() && removed.is_empty() {
+            return Ok(0);
+        }
+        let tx = self.conn.transaction()?;
+        for (key, data) in changed.iter() {
+            write_record(&tx, *key, data)?;
+        }
+        for key in removed.iter() {
+            delete_record(&tx, *key)?;
+        }
+        tx.commit()?;
+        for key in removed.iter() {
+            self.written.remove(key);
+        }
+        self.written.extend(present);
+        Ok(changed.len() + removed.len())
+    }
+
+    fn load(&mut self) -> Result<DataBase> {
+        let mut meta = HashMap::new();
+        {
+            let mut stmt = self.conn.prepare("SELECT key, value FROM meta")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?;
+            for row in rows {
+                let (key, value) = row?;
+                meta.insert(key, value);
+            }
+        }
+        let mut items: HashMap<usize, Vec<SerdeLockLayer<RssItem>>> = HashMap::new();
+        {
+            let mut stmt = self
+                .conn
+                .prepare("SELECT rss_id, id, data FROM rss_item ORDER BY rss_id, id")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)? as usize,
+                    row.get::<_, i64>(1)? as usize,
+                    row.get::<_, String>(2)?,
+                ))
+            })?;
+            for row in rows {
+                let (rss_id, id, data) = row?;
+                let item: RssItem = serde_json::from_str(&data)
+                    .with_context(|| format!("Parse item {} of rss {}", id, rss_id))?;
+                self.written
+                    .insert(RecordKey::Item(rss_id, id), hash(&data));
+                items.entry(rss_id).or_default().push(item.into());
+            }
+        }
+        let mut rss_list = HashMap::new();
+        {
+            let mut stmt = self.conn.prepare("SELECT id, data FROM rss")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, i64>(0)? as usize, row.get::<_, String>(1)?))
+            })?;
+            for row in rows {
+                let (id, data) = row?;
+                let rss = parse_rss(&data, items.remove(&id).unwrap_or_default())
+                    .with_context(|| format!("Parse rss {}", id))?;
+                self.written.insert(RecordKey::Rss(id), hash(&data));
+                rss_list.insert(id, SerdeLockLayer::new(rss));
+            }
+        }
+        let mut series_list = HashMap::new();
+        {
+            let mut stmt = self.conn.prepare("SELECT id, data FROM series")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, i64>(0)? as usize, row.get::<_, String>(1)?))
+            })?;
+            for row in rows {
+                let (id, data) = row?;
+                let series: Series =
+                    serde_json::from_str(&data).with_context(|| format!("Parse series {}", id))?;
+                self.written.insert(RecordKey::Series(id), hash(&data));
+                series_list.insert(id, series);
+            }
+        }
+        let index = |key: &'static str| -> Result<usize> {
+            meta.get(key)
+                .map(|v| v.parse::<usize>())
+                .transpose()
+                .map(|v| v.unwrap_or(0))
+                .map_err(|e| anyhow!("Parse {}: {}", key, e))
+        };
+        let db = DataBase {
+            rss_list,
+            rss_id_index: index("rss_id_index")?,
+            series_list,
+            series_id_index: index("series_id_index")?,
+        };
+        for (key, value) in meta_records(&db) {
+            self.written.insert(key, hash(&value));
+        }
+        Ok(db)
+    }
+}
+
+/// 读取旧版本的 `db.bin`，文件不存在或已经是 SQLite 数据库时返回 `None`
+/// 先只读取文件头判断格式，避免每次启动都读入整个数据库
+fn read_legacy(path: &Path) -> Result<Option<Vec<u8>>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut header = Vec::with_capacity(SQLITE_HEADER.len());
+    file.take(SQLITE_HEADER.len() as u64)
+        .read_to_end(&mut header)?;
+    if header.is_empty() || header == SQLITE_HEADER {
+        return Ok(None);
+    }
+    Ok(Some(fs::read(path)?))
+}
+
+/// 执行 `nekodl db migrate`，返回每一步的说明，`dry_run` 时不修改数据库
+pub async fn migrate(path: &str, dry_run: bool) -> Result<Vec<String>> {
+    let path = Path::new(path).to_owned();
+    if !path.exists() {
+        return Ok(vec![format!("No database at {}", path.display())]);
+    }
+    if let Some(data) = read_legacy(&path)? {
+        let db = legacy::parse(&data).context("Parse legacy database")?;
+        let mut items = 0;
+        for rss in db.rss_list.values() {
+            items += rss.read().await.items.len();
+        }
+        if !dry_run {
+            Store::open_or_import(&path.to_string_lossy()).await?;
+        }
+        return Ok(vec![format!(
+            "Version 0 -> {}: import {} feeds and {} items from legacy database, keep the original file as {}.legacy",
+            SCHEMA_VERSION,
+            db.rss_list.len(),
+            items,
+            path.display()
+        )]);
+    }
+    let report = tokio::task::spawn_blocking(move || {
+        let mut conn = Connection::open(&path)?;
+        migration::migrate(&mut conn, dry_run)
+    })
+    .await??;
+    if report.steps.is_empty() {
+        return Ok(vec![format!(
+            "Schema version {} is up to date",
+            report.from
+        )]);
+    }
+    let mut lines = Vec::new();
+    for step in report.steps {
+        lines.push(format!("Version {}: {}", step.version, step.description));
+        if step.changes.is_empty() {
+            lines.push("  no changes".to_owned());
+        }
+        for change in step.changes {
+            lines.push(format!("  {}", change));
+        }
+    }
+    Ok(lines)
+}
+
+impl Store {
+    /// 打开数据库，结构版本较旧时自动迁移到当前版本
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut conn = Connection::open(path)?;
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")?;
+        let report = migration::migrate(&mut conn, false)?;
+        for step in report.steps {
+            info!(
+                "Migrated database to version {}: {} ({} changes)",
+                step.version,
+                step.description,
+                step.changes.len()
+            );
+        }
+        Ok(Self {
+            inner: Arc::new(Mutex::new(Inner {
+                conn,
+                written: HashMap::new(),
+            })),
+            saving: Arc::new(AsyncMutex::new(())),
+        })
+    }
+
+    fn open_and_load(path: &Path) -> Result<(Self, DataBase)> {
+        let store = Self::open(path)?;
+        let db = store.inner.lock().unwrap().load()?;
+        Ok((store, db))
+    }
+
+    /// 数据库无法读取时，将其重命名为 `<path>.corrupt`，并从最新的可用备份恢复
+    fn recover(path: &Path) -> Result<(Self, DataBase)> {
+        let corrupt = backup::set_aside(path)?;
+        // WAL 文件属于损坏的数据库，不能用于备份
+        for suffix in ["-wal", "-shm"] {
+            let mut from = path.as_os_str().to_owned();
+            from.push(suffix);
+            let mut to = corrupt.as_os_str().to_owned();
+            to.push(suffix);
+            if Path::new(&from).exists() {
+                fs::rename(&from, &to)?;
+            }
+        }
+        for backup in backup::backups(path) {
+            fs::copy(&backup, path)?;
+            match Self::open_and_load(path) {
+                Ok(res) => {
+                    warn!("Recovered database from {}", backup.display());
+                    return Ok(res);
+                }
+                Err(e) => {
+                    error!("Failed to load backup {}: {:#}", backup.display(), e);
+                    fs::remove_file(path)?;
+                }
+            }
+        }
+        Err(anyhow!("No usable backup of {}", path.display()))
+    }
+
+    /// 打开数据库并读取数据。
+    /// `path` 是旧版本的 `db.bin` 时，先将其重命名为 `<path>.legacy`，再导入到新建的数据库中，
+    /// 存在附属文件 `<path>.json` 时从附属文件导入，其中包含 `db.bin` 中没有的字段；
+    /// 数据库损坏时从备份恢复。
+    pub async fn open_or_import(path: &str) -> Result<(Self, DataBase)> {
+        let path = Path::new(path).to_owned();
+        let (store, db, imported) = tokio::task::spawn_blocking(move || -> Result<_> {
+            // 旧版本不会产生备份，有备份时无法识别的文件视为损坏的数据库
+            let legacy = read_legacy(&path)?.filter(|_| backup::backups(&path).is_empty());
+            let Some(data) = legacy else {
+                let (store, db) = match Self::open_and_load(&path) {
+                    Ok(res) => res,
+                    Err(e) if !backup::backups(&path).is_empty() => {
+                        error!("Failed to load database {}: {:#}", path.display(), e);
+                        Self::recover(&path).context(e)?
+                    }
+                    Err(e) => return Err(e),
+                };
+                return Ok((store, db, false));
+            };
+            info!("Importing legacy database {}", path.display());
+            let mut side = path.clone().into_os_string();
+            side.push(".json");
+            let side = PathBuf::from(side);
+            let db = match fs::read(&side) {
+                Ok(data) => legacy::parse_side(&data).context("Parse legacy database")?,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    legacy::parse(&data).context("Parse legacy database")?
+                }
+                Err(e) => return Err(e.into()),
+            };
+            for file in [&path, &side] {
+                if file.exists() {
+                    let mut backup = file.clone().into_os_string();
+                    backup.push(".legacy");
+                    fs::rename(file, &backup)?;
+                }
+            }
+            Ok((Self::open(&path)?, db, true))
+        })
+        .await??;
+        if !imported {
+            return Ok((store, db));
+        }
+        let db = RwLock::new(db);
+        store.save(&db).await?;
+        Ok((store, db.into_inner()))
+    }
+
+    /// 将当前数据库完整复制为最新的备份，保留 `count` 个备份
+    pub async fn backup(&self, count: usize) -> Result<()> {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || {
+            let inner = inner.lock().unwrap();
+            let Some(path) = inner.conn.path().map(PathBuf::from) else {
+                return Err(anyhow!("Database has no file"));
+            };
+            let mut tmp = path.clone().into_os_string();
+            tmp.push(".bak.tmp");
+            let tmp = PathBuf::from(tmp);
+            if tmp.exists() {
+                fs::remove_file(&tmp)?;
+            }
+            inner
+                .conn
+                .execute("VACUUM INTO ?1", params![tmp.to_string_lossy()])?;
+            File::open(&tmp)?.sync_all()?;
+            backup::rotate(&path, &tmp, count)
+        })
+        .await?
+    }
+
+    /// 读取保存的登录会话，没有保存时为空
+    pub async fn load_sessions(&self) -> Result<Sessions> {
+        self.load_meta(SESSIONS_KEY).await.context("Load sessions")
+    }
+
+    /// 保存序列化后的登录会话，为 `None` 时删除已保存的会话
+    pub async fn save_sessions(&self, data: Option<String>) -> Result<()> {
+        self.save_meta(SESSIONS_KEY, data).await
+    }
+
+    /// 读取保存的 API 密钥，没有保存时为空
+    pub async fn load_api_keys(&self) -> Result<ApiKeys> {
+        self.load_meta(API_KEYS_KEY).await.context("Load API keys")
+    }
+
+    /// 保存序列化后的 API 密钥
+    pub async fn save_api_keys(&self, data: String) -> Result<()> {
+        self.save_meta(API_KEYS_KEY, Some(data)).await
+    }
+
+    /// 读取保存的下载任务，没有保存时为空
+    pub async fn load_download_tasks(&self) -> Result<HashMap<usize, DownloadTask>> {
+        self.load_meta(DOWNLOAD_TASKS_KEY)
+            .await
+            .context("Load download tasks")
+    }
+
+    /// 保存序列化后的下载任务
+    pub async fn save_download_tasks(&self, data: String) -> Result<()> {
+        self.save_meta(DOWNLOAD_TASKS_KEY, Some(data)).await
+    }
+
+    /// 读取 meta 表中单独保存的 JSON 数据，没有保存时使用默认值
+    async fn load_meta<T>(&self, key: &'static str) -> Result<T>
+    where
+        T: DeserializeOwned + Default + Send + 'static,
+    {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || {
+            let data: Option<String> = inner
+                .lock()
+                .unwrap()
+                .conn
+                .query_row(
+                    "SELECT value FROM meta WHERE key = ?1",
+                    params![key],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok(data
+                .map(|data| serde_json::from_str(&data))
+                .transpose()?
+                .unwrap_or_default())
+        })
+        .await?
+    }
+
+    /// 写入 meta 表中单独保存的数据，为 `None` 时删除
+    async fn save_meta(&self, key: &'static str, data: Option<String>) -> Result<()> {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || {
+            let inner = inner.lock().unwrap();
+            match data {
+                Some(data) => inner.conn.execute(
+                    "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+                    params![key, data],
+                )?,
+                None => inner
+                    .conn
+                    .execute("DELETE FROM meta WHERE key = ?1", params![key])?,
+            };
+            Ok(())
+        })
+        .await?
+    }
+
+    async fn apply(&self, snapshot: Snapshot) -> Result<usize> {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || inner.lock().unwrap().apply(snapshot)).await?
+    }
+
+    /// 保存整个数据库，只写入有变化的记录。
+    /// 数据库的读锁只在复制订阅句柄时持有，保存过程中不会阻塞对数据库的修改。
+    pub async fn save(&self, db: &RwLock<DataBase>) -> Result<()> {
+        let _saving = self.saving.lock().await;
+        let (mut records, rss_list) = {
+            let db = db.read().await;
+            let mut records = meta_records(&db);
+            for series in db.series_list.values() {
+                records.push((RecordKey::Series(series.id), serde_json::to_string(series)?));
+            }
+            (records, db.rss_list.values().cloned().collect::<Vec<_>>())
+        };
+        for rss in rss_list.iter() {
+            snapshot_rss(rss, &mut records).await?;
+        }
+        let count = self
+            .apply(Snapshot {
+                records,
+                rss_id: None,
+            })
+            .await?;
+        if count > 0 {
+            info!("Saved {} records", count);
+        }
+        Ok(())
+    }
+
+    /// 只保存一个订阅及其条目，订阅已被删除时删除对应的记录
+    pub async fn save_rss(&self, db: &RwLock<DataBase>, rss_id: usize) -> Result<()> {
+        let _saving = self.saving.lock().await;
+        // ID 计数器不在删除范围内，随订阅一起写入
+        let (mut records, rss) = {
+            let db = db.read().await;
+            (meta_records(&db), db.rss_list.get(&rss_id).cloned())
+        };
+        if let Some(rss) = rss {
+            snapshot_rss(&rss, &mut records).await?;
+        }
+        self.apply(Snapshot {
+            records,
+            rss_id: Some(rss_id),
+        })
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rss::RssItemStatus;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_import_and_save() {
+        let dir = std::env::temp_dir().join(format!("nekodl-store-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("db.bin");
+        fs::write(&path, legacy::sample()).unwrap();
+
+        let (store, db) = Store::open_or_import(path.to_str().unwrap()).await.unwrap();
+        assert!(dir.join("db.bin.legacy").exists());
+        assert_eq!(db.rss_id_index, 1);
+        {
+            let rss = db.rss_list[&1].read().await;
+            assert_eq!(rss.items.len(), 2);
+            assert_eq!(rss.items[0].read().await.status, RssItemStatus::Downloaded);
+        }
+
+        // 修改一个条目、删除一个条目并新增剧集
+        {
+            let mut rss = db.rss_list[&1].write().await;
+            rss.items[0].write().await.status = RssItemStatus::Read;
+            rss.items.pop();
+        }
+        let db = Arc::new(RwLock::new(db));
+        store.save_rss(&db, 1).await.unwrap();
+
+        // 条目被写锁定时，保存任务等待该条目，但不阻塞对数据库的修改
+        let item = db.read().await.rss_list[&1].read().await.items[0].clone();
+        let guard = item.write().await;
+        let save = tokio::spawn({
+            let (store, db) = (store.clone(), db.clone());
+            async move { store.save(&db).await }
+        });
+        {
+            let mut db = tokio::time::timeout(Duration::from_secs(1), db.write())
+                .await
+                .unwrap();
+            db.series_id_index = 1;
+            db.series_list.insert(
+                1,
+                serde_json::from_str(r#"{"id":1,"name":"Frieren","rss_ids":[1]}"#).unwrap(),
+            );
+        }
+        drop(guard);
+        save.await.unwrap().unwrap();
+        store.save(&db).await.unwrap();
+        drop(store);
+
+        let (_, db) = Store::open_or_import(path.to_str().unwrap()).await.unwrap();
+        let rss = db.rss_list[&1].read().await;
+        assert_eq!(rss.items.len(), 1);
+        assert_eq!(rss.items[0].read().await.status, RssItemStatus::Read);
+        assert!(rss.auto_download);
+        assert_eq!(db.series_list[&1].name, "Frieren");
+        assert_eq!(db.series_id_index, 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_save_rss_during_save() {
+        let dir = std::env::temp_dir().join(format!("nekodl-store-race-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("db.bin");
+        fs::write(&path, legacy::sample()).unwrap();
+        let (store, db) = Store::open_or_import(path.to_str().unwrap()).await.unwrap();
+        let db = Arc::new(RwLock::new(db));
+
+        // 完整保存等待被锁定的条目时，新增订阅并单独保存
+        let item = db.read().await.rss_list[&1].read().await.items[0].clone();
+        let guard = item.write().await;
+        let save = tokio::spawn({
+            let (store, db) = (store.clone(), db.clone());
+            async move { store.save(&db).await }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        {
+            let mut db = db.write().await;
+            db.rss_id_index = 2;
+            db.rss_list.insert(
+                2,
+                Rss::new(
+                    2,
+                    "https://nyaa.si/?page=rss".to_owned(),
+                    "nyaa".to_owned(),
+                    String::new(),
+                )
+                .into(),
+            );
+        }
+        let save_rss = tokio::spawn({
+            let (store, db) = (store.clone(), db.clone());
+            async move { store.save_rss(&db, 2).await }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        drop(guard);
+        save.await.unwrap().unwrap();
+        save_rss.await.unwrap().unwrap();
+        drop(store);
+
+        // 完整保存的快照中没有新订阅，但不会删除其记录
+        let (_, db) = Store::open_or_import(path.to_str().unwrap()).await.unwrap();
+        assert!(db.rss_list.contains_key(&2));
+        assert_eq!(db.rss_id_index, 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_import_side_file() {
+        let dir = std::env::temp_dir().join(format!("nekodl-store-side-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("db.bin");
+        fs::write(&path, legacy::sample()).unwrap();
+        // 附属文件中的状态和标签不在 db.bin 中
+        let side = serde_json::json!({
+            "rss_list": {"1": {
+                "id": 1,
+                "url": "https://mikanani.me/RSS/Bangumi?bangumiId=3141",
+                "title": "Frieren",
+                "description": "",
+                "items": [{
+                    "title": "[ANi] Frieren - 01",
+                    "link": "https://example.com/0.torrent",
+                    "description": "",
+                    "status": "Ignored",
+                    "torrent": null,
+                    "id": 0
+                }],
+                "update_time": std::time::SystemTime::UNIX_EPOCH,
+                "update_interval": std::time::Duration::from_secs(3600),
+                "status": "Updated",
+                "auto_download": true,
+                "tags": ["anime"]
+            }},
+            "rss_id_index": 1
+        });
+        fs::write(dir.join("db.bin.json"), side.to_string()).unwrap();
+
+        let (_, db) = Store::open_or_import(path.to_str().unwrap()).await.unwrap();
+        assert!(dir.join("db.bin.legacy").exists());
+        assert!(dir.join("db.bin.json.legacy").exists());
+        let rss = db.rss_list[&1].read().await;
+        assert_eq!(rss.tags, vec!["anime".to_owned()]);
+        assert_eq!(rss.items.len(), 1);
+        assert_eq!(rss.items[0].read().await.status, RssItemStatus::Ignored);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_recover_from_backup() {
+        let dir = std::env::temp_dir().join(format!("nekodl-recover-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("nekodl.db");
+        let path_str = path.to_str().unwrap();
+
+        let (store, db) = Store::open_or_import(path_str).await.unwrap();
+        let db = RwLock::new(db);
+        db.write().await.rss_id_index = 7;
+        store.save(&db).await.unwrap();
+        store.backup(2).await.unwrap();
+        db.write().await.rss_id_index = 8;
+        store.save(&db).await.unwrap();
+        drop(store);
+
+        fs::write(&path, b"garbage").unwrap();
+        let (_, db) = Store::open_or_import(path_str).await.unwrap();
+        assert_eq!(db.rss_id_index, 7);
+        assert!(dir.join("nekodl.db.corrupt").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
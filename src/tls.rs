@@ -0,0 +1,138 @@
+use std::{
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Context, Result};
+use salvo::conn::rustls::{Keycert, RustlsConfig};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::watch, time::sleep};
+use tokio_stream::{wrappers::WatchStream, Stream, StreamExt};
+use tracing::{error, info};
+use ts_rs::TS;
+
+use crate::{backup, listen::BindAddress};
+
+/// 检查证书文件是否被修改的间隔
+const WATCH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// HTTPS 设置，只用于 TCP 监听器
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct TlsOptions {
+    /// PEM 格式的证书链
+    pub cert_path: String,
+    /// PEM 格式的私钥
+    pub key_path: String,
+    /// 证书文件不存在时生成自签名证书
+    #[serde(default)]
+    pub self_signed: bool,
+}
+
+/// 读取证书和私钥
+fn load(options: &TlsOptions) -> Result<RustlsConfig> {
+    let cert = std::fs::read(&options.cert_path)
+        .with_context(|| format!("Read certificate {}", options.cert_path))?;
+    let key = std::fs::read(&options.key_path)
+        .with_context(|| format!("Read private key {}", options.key_path))?;
+    let contains = |data: &[u8], label: &str| {
+        String::from_utf8_lossy(data).contains(&format!("-----BEGIN {}", label))
+    };
+    if !contains(&cert, "CERTIFICATE") {
+        return Err(anyhow!("{} is not a PEM certificate", options.cert_path));
+    }
+    // PKCS#8、PKCS#1（RSA）和 SEC1（EC）格式的私钥
+    if !["PRIVATE KEY", "RSA PRIVATE KEY", "EC PRIVATE KEY"]
+        .iter()
+        .any(|label| contains(&key, label))
+    {
+        return Err(anyhow!("{} is not a PEM private key", options.key_path));
+    }
+    Ok(RustlsConfig::new(Keycert::new().cert(cert).key(key)))
+}
+
+/// 启用自签名证书且证书文件不存在时，为 localhost 和监听的 IP 地址生成证书
+pub fn ensure_certificate(options: &TlsOptions, addresses: &[BindAddress]) -> Result<()> {
+    if !options.self_signed || Path::new(&options.cert_path).exists() {
+        return Ok(());
+    }
+    let mut names = vec!["localhost".to_owned()];
+    for address in addresses {
+        if let BindAddress::Tcp(addr) = address {
+            if !addr.ip().is_unspecified() {
+                names.push(addr.ip().to_string());
+            }
+        }
+    }
+    let cert = rcgen::generate_simple_self_signed(names)?;
+    for path in [&options.cert_path, &options.key_path] {
+        if let Some(parent) = Path::new(path).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+    backup::atomic_write_private(
+        Path::new(&options.key_path),
+        cert.key_pair.serialize_pem().as_bytes(),
+    )?;
+    backup::atomic_write(Path::new(&options.cert_path), cert.cert.pem().as_bytes())?;
+    info!("Generated self-signed certificate {}", options.cert_path);
+    Ok(())
+}
+
+/// 证书配置流，每次收到重新加载的通知时读取证书，读取失败时继续使用原来的证书
+pub fn config_stream(
+    options: TlsOptions,
+    reload: watch::Receiver<()>,
+) -> Result<impl Stream<Item = RustlsConfig> + Send + Unpin + 'static> {
+    // 启动时检查一次，证书不可用时直接返回错误
+    load(&options)?;
+    Ok(
+        WatchStream::new(reload).filter_map(move |()| match load(&options) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                error!("Failed to reload certificate: {:#}", e);
+                None
+            }
+        }),
+    )
+}
+
+fn modified(options: &TlsOptions) -> [Option<SystemTime>; 2] {
+    [&options.cert_path, &options.key_path]
+        .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+}
+
+/// 收到 SIGHUP 或证书文件被修改时通知重新加载证书
+pub async fn tls_reload_task(options: TlsOptions, reload: watch::Sender<()>) {
+    #[cfg(unix)]
+    let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(signal) => Some(signal),
+        Err(e) => {
+            error!("Failed to listen for SIGHUP: {}", e);
+            None
+        }
+    };
+    let mut last_modified = modified(&options);
+    loop {
+        #[cfg(unix)]
+        let hangup_received = async {
+            match hangup.as_mut() {
+                Some(signal) => signal.recv().await,
+                None => std::future::pending().await,
+            }
+        };
+        #[cfg(not(unix))]
+        let hangup_received = std::future::pending::<Option<()>>();
+        tokio::select! {
+            _ = hangup_received => info!("Reloading certificate on SIGHUP"),
+            _ = sleep(WATCH_INTERVAL) => {
+                let current = modified(&options);
+                if current == last_modified {
+                    continue;
+                }
+                last_modified = current;
+                info!("Reloading certificate {}", options.cert_path);
+            }
+        }
+        reload.send_replace(());
+    }
+}
@@ -5,6 +5,7 @@ use std::sync::{Arc, Weak};
 use tokio::sync::RwLock;
 
 use crate::rss::{ItemTorrent, RssItem, TorrentFileInfo};
+use crate::torrent_cache::TorrentCache;
 
 #[derive(Serialize)]
 pub struct TorrentInfo {
@@ -51,12 +52,36 @@ pub async fn fetch_torrent_info<'a>(
     })
 }
 
-pub async fn fetch_torrent_for_item<'a>(
-    add_torrent: AddTorrent<'a>,
+/// 获取条目对应的种子，优先使用本地缓存的种子文件，缓存不存在时从条目链接下载并写入缓存
+pub async fn item_add_torrent(
+    cache: &TorrentCache,
+    lock: &Weak<RwLock<RssItem>>,
+) -> Result<AddTorrent<'static>> {
+    let (link, info_hash) = {
+        let item = lock.upgrade().context("Can not upgread Weak")?;
+        let item = item.read().await;
+        (item.link.clone(), item.info_hash.clone())
+    };
+    Ok(match cache.fetch(&link, info_hash.as_deref()).await? {
+        Some((info_hash, data)) => {
+            lock.upgrade()
+                .context("Can not upgread Weak")?
+                .write()
+                .await
+                .info_hash = Some(info_hash);
+            AddTorrent::TorrentFileBytes(data)
+        }
+        None => AddTorrent::Url(link.into()),
+    })
+}
+
+pub async fn fetch_torrent_for_item(
+    cache: &TorrentCache,
     session: Arc<Session>,
     trackers: Vec<String>,
     lock: Weak<RwLock<RssItem>>,
 ) -> Result<ItemTorrent> {
+    let add_torrent = item_add_torrent(cache, &lock).await?;
     let info = fetch_torrent_info(add_torrent, session, trackers).await?;
     let res = ItemTorrent {
         files: info
@@ -0,0 +1,238 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use salvo::hyper::body::Bytes;
+use sha1::{Digest, Sha1};
+
+use crate::utils::rand_str;
+
+/// 种子文件缓存，以 infohash 为键保存在 `session_path/torrents` 下
+#[derive(Debug, Clone)]
+pub struct TorrentCache {
+    root: PathBuf,
+}
+
+impl TorrentCache {
+    pub fn new(session_path: &str) -> Self {
+        let mut root = PathBuf::from(session_path);
+        root.push("torrents");
+        Self { root }
+    }
+
+    /// 种子文件在缓存中的路径，infohash 不是 40 位十六进制时返回错误，以免访问缓存目录以外的文件
+    pub fn path(&self, info_hash: &str) -> Result<PathBuf> {
+        if info_hash.len() != 40 || !info_hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(anyhow!("Invalid infohash {:?}", info_hash));
+        }
+        Ok(self
+            .root
+            .join(format!("{}.torrent", info_hash.to_lowercase())))
+    }
+
+    /// 读取缓存的种子文件，不存在时返回 `None`
+    pub async fn get(&self, info_hash: &str) -> Result<Option<Bytes>> {
+        match tokio::fs::read(self.path(info_hash)?).await {
+            Ok(data) => Ok(Some(data.into())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 将种子文件写入缓存，返回其 infohash
+    pub async fn put(&self, data: &[u8]) -> Result<String> {
+        let info_hash = info_hash(data)?;
+        self.write(&info_hash, data).await?;
+        Ok(info_hash)
+    }
+
+    async fn write(&self, info_hash: &str, data: &[u8]) -> Result<()> {
+        let path = self.path(info_hash)?;
+        if tokio::fs::try_exists(&path).await? {
+            return Ok(());
+        }
+        tokio::fs::create_dir_all(&self.root).await?;
+        // 先写入临时文件再重命名，避免留下不完整的种子文件；
+        // 临时文件名带有随机后缀，同时写入同一种子时互不影响
+        let tmp = self.root.join(format!("{}.{}.tmp", info_hash, rand_str(8)));
+        if let Err(e) = tokio::fs::write(&tmp, data).await {
+            let _ = tokio::fs::remove_file(&tmp).await;
+            return Err(e.into());
+        }
+        tokio::fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+
+    /// 从缓存或链接获取种子文件。
+    /// 已知 infohash 且缓存命中时直接返回缓存内容，否则从链接下载并写入缓存，
+    /// 下载的种子与已知的 infohash 不一致时返回错误。
+    /// 磁力链接无法获取种子文件，返回 `None`。
+    pub async fn fetch(
+        &self,
+        link: &str,
+        info_hash: Option<&str>,
+    ) -> Result<Option<(String, Bytes)>> {
+        if let Some(info_hash) = info_hash {
+            if let Some(data) = self.get(info_hash).await? {
+                return Ok(Some((info_hash.to_lowercase(), data)));
+            }
+        }
+        if link.starts_with("magnet:") {
+            return Ok(None);
+        }
+        let data = reqwest::get(link)
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let actual = self::info_hash(&data)
+            .with_context(|| format!("Invalid torrent file from {}", link))?;
+        if let Some(expected) = info_hash {
+            if !expected.eq_ignore_ascii_case(&actual) {
+                return Err(anyhow!(
+                    "Torrent file from {} has infohash {}, expected {}",
+                    link,
+                    actual,
+                    expected
+                ));
+            }
+        }
+        self.write(&actual, &data).await?;
+        Ok(Some((actual, data)))
+    }
+}
+
+/// 计算种子文件的 infohash（info 字典的 SHA-1），以小写十六进制表示
+pub fn info_hash(data: &[u8]) -> Result<String> {
+    let (start, end) = find_info_span(data)?;
+    let mut hasher = Sha1::new();
+    hasher.update(&data[start..end]);
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// 找到顶层字典中 `info` 键对应值在数据中的范围
+fn find_info_span(data: &[u8]) -> Result<(usize, usize)> {
+    if data.first() != Some(&b'd') {
+        return Err(anyhow!("Torrent file is not a bencoded dictionary"));
+    }
+    let mut pos = 1;
+    while data.get(pos) != Some(&b'e') {
+        let (key, value_start) = read_bytes(data, pos)?;
+        let value_end = skip_value(data, value_start)?;
+        if key == b"info" {
+            return Ok((value_start, value_end));
+        }
+        pos = value_end;
+    }
+    Err(anyhow!("Torrent file has no info dictionary"))
+}
+
+/// 读取一个字节串，返回内容和之后的位置
+fn read_bytes(data: &[u8], pos: usize) -> Result<(&[u8], usize)> {
+    let colon = data[pos..]
+        .iter()
+        .position(|c| *c == b':')
+        .context("Unterminated byte string length")?
+        + pos;
+    let len: usize = std::str::from_utf8(&data[pos..colon])?.parse()?;
+    // 长度来自下载的文件，需要检查溢出
+    let end = (colon + 1)
+        .checked_add(len)
+        .filter(|end| *end <= data.len())
+        .context("Byte string out of range")?;
+    Ok((&data[colon + 1..end], end))
+}
+
+/// 跳过一个 bencode 值，返回之后的位置。
+/// 不使用递归，嵌套很深的列表和字典不会导致栈溢出
+fn skip_value(data: &[u8], mut pos: usize) -> Result<usize> {
+    // 尚未结束的列表和字典的层数
+    let mut depth = 0usize;
+    loop {
+        match data.get(pos).context("Unexpected end of torrent file")? {
+            b'i' => {
+                pos += data[pos..]
+                    .iter()
+                    .position(|c| *c == b'e')
+                    .context("Unterminated integer")?
+                    + 1
+            }
+            b'l' | b'd' => {
+                depth += 1;
+                pos += 1;
+            }
+            b'e' if depth > 0 => {
+                depth -= 1;
+                pos += 1;
+            }
+            b'0'..=b'9' => pos = read_bytes(data, pos)?.1,
+            c => return Err(anyhow!("Unexpected byte {:?} in torrent file", *c as char)),
+        }
+        if depth == 0 {
+            return Ok(pos);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_info_hash() {
+        let info = b"d6:lengthi12e4:name5:a.txt12:piece lengthi16384e6:pieces0:e";
+        let mut data = b"d8:announce9:udp://x:14:info".to_vec();
+        data.extend_from_slice(info);
+        data.push(b'e');
+        let mut hasher = Sha1::new();
+        hasher.update(info);
+        assert_eq!(
+            info_hash(&data).unwrap(),
+            format!("{:x}", hasher.finalize())
+        );
+    }
+
+    #[test]
+    fn test_path() {
+        let cache = TorrentCache::new("session");
+        assert!(cache
+            .path("0123456789ABCDEF0123456789ABCDEF01234567")
+            .unwrap()
+            .ends_with("torrents/0123456789abcdef0123456789abcdef01234567.torrent"));
+        assert!(cache.path("../../../../etc/passwd").is_err());
+        assert!(cache
+            .path("0123456789abcdef0123456789abcdef0123456/")
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_put_concurrent() {
+        let dir = std::env::temp_dir().join(format!("torrent_cache_{}", rand_str(8)));
+        let cache = TorrentCache::new(dir.to_str().unwrap());
+        let data = b"d4:infod6:lengthi12e4:name5:a.txtee";
+        let (a, b) = tokio::join!(cache.put(data), cache.put(data));
+        let info_hash = a.unwrap();
+        assert_eq!(info_hash, b.unwrap());
+        assert_eq!(
+            cache.get(&info_hash).await.unwrap().unwrap().as_ref(),
+            data.as_slice()
+        );
+        // 不会留下临时文件
+        assert_eq!(std::fs::read_dir(dir.join("torrents")).unwrap().count(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_info_hash_invalid() {
+        assert!(info_hash(b"d8:announce3:abce").is_err());
+        assert!(info_hash(b"not a torrent").is_err());
+        // 长度溢出
+        assert!(info_hash(b"d4:info18446744073709551615:e").is_err());
+        // 嵌套过深
+        let mut data = b"d4:info".to_vec();
+        data.resize(data.len() + (1 << 20), b'l');
+        assert!(info_hash(&data).is_err());
+        data.resize(data.len() + (1 << 20), b'e');
+        data.push(b'e');
+        assert!(info_hash(&data).is_ok());
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::HashMap,
+};
+
+use tokio::sync::broadcast;
+
+/// 变更通知通道的容量，接收方处理过慢时会丢失较早的通知
+const CHANGE_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Insert,
+    Update,
+    Remove,
+}
+
+/// 一次数据变更，`path` 为数据所在命名空间的路径，根命名空间为空字符串
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub path: String,
+    pub type_id: TypeId,
+    pub type_name: &'static str,
+    pub kind: ChangeKind,
+}
+
+impl Change {
+    pub fn is<T: Any>(&self) -> bool {
+        self.type_id == TypeId::of::<T>()
+    }
+}
+
+/// 按类型保存数据的命名空间，每个命名空间中同一类型只保存一个值。
+/// 子命名空间以名称区分，路径用 `/` 分隔，例如 `rss/3`。
+/// 同一棵树中的所有命名空间共享一个变更通知通道。
+pub struct TypedStore {
+    path: String,
+    data: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    children: HashMap<String, TypedStore>,
+    changes: broadcast::Sender<Change>,
+}
+
+impl Default for TypedStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|s| !s.is_empty())
+}
+
+impl TypedStore {
+    pub fn new() -> Self {
+        Self {
+            path: String::new(),
+            data: HashMap::new(),
+            children: HashMap::new(),
+            changes: broadcast::channel(CHANGE_CAPACITY).0,
+        }
+    }
+
+    /// 当前命名空间的路径
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// 订阅整棵树的变更通知
+    pub fn subscribe(&self) -> broadcast::Receiver<Change> {
+        self.changes.subscribe()
+    }
+
+    fn notify<T: Any>(&self, kind: ChangeKind) {
+        // 没有接收方时忽略
+        let _ = self.changes.send(Change {
+            path: self.path.clone(),
+            type_id: TypeId::of::<T>(),
+            type_name: type_name::<T>(),
+            kind,
+        });
+    }
+
+    /// 按路径查找子命名空间，空路径为自身
+    pub fn child(&self, path: &str) -> Option<&TypedStore> {
+        segments(path).try_fold(self, |node, name| node.children.get(name))
+    }
+
+    pub fn child_mut(&mut self, path: &str) -> Option<&mut TypedStore> {
+        segments(path).try_fold(self, |node, name| node.children.get_mut(name))
+    }
+
+    /// 按路径获取子命名空间，不存在时依次创建
+    pub fn child_or_create(&mut self, path: &str) -> &mut TypedStore {
+        segments(path).fold(self, |node, name| {
+            let child_path = if node.path.is_empty() {
+                name.to_owned()
+            } else {
+                format!("{}/{}", node.path, name)
+            };
+            let changes = node.changes.clone();
+            node.children
+                .entry(name.to_owned())
+                .or_insert_with(|| TypedStore {
+                    path: child_path,
+                    data: HashMap::new(),
+                    children: HashMap::new(),
+                    changes,
+                })
+        })
+    }
+
+    /// 删除子命名空间及其中的所有数据，不会为其中的数据逐个发送通知
+    pub fn remove_child(&mut self, path: &str) -> Option<TypedStore> {
+        let (parent, name) = match path.trim_end_matches('/').rsplit_once('/') {
+            Some((parent, name)) => (self.child_mut(parent)?, name),
+            None => (self, path.trim_end_matches('/')),
+        };
+        parent.children.remove(name)
+    }
+
+    /// 直接子命名空间的名称
+    pub fn children(&self) -> impl Iterator<Item = &str> {
+        self.children.keys().map(|name| name.as_str())
+    }
+
+    pub fn get<T: Any + Send + Sync>(&self, path: &str) -> Option<&T> {
+        self.child(path)?
+            .data
+            .get(&TypeId::of::<T>())?
+            .downcast_ref()
+    }
+
+    /// 插入数据，返回原来的值，路径中不存在的命名空间会被创建
+    pub fn insert<T: Any + Send + Sync>(&mut self, path: &str, value: T) -> Option<T> {
+        let node = self.child_or_create(path);
+        let old = node
+            .data
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|old| old.downcast().ok())
+            .map(|old| *old);
+        node.notify::<T>(if old.is_some() {
+            ChangeKind::Update
+        } else {
+            ChangeKind::Insert
+        });
+        old
+    }
+
+    /// 修改已有的数据并发送通知，数据不存在时返回 `None`
+    pub fn update<T: Any + Send + Sync, R>(
+        &mut self,
+        path: &str,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> Option<R> {
+        let node = self.child_mut(path)?;
+        let value = node.data.get_mut(&TypeId::of::<T>())?.downcast_mut()?;
+        let res = f(value);
+        node.notify::<T>(ChangeKind::Update);
+        Some(res)
+    }
+
+    pub fn remove<T: Any + Send + Sync>(&mut self, path: &str) -> Option<T> {
+        let node = self.child_mut(path)?;
+        let old = node.data.remove(&TypeId::of::<T>())?.downcast().ok()?;
+        node.notify::<T>(ChangeKind::Remove);
+        Some(*old)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Interval(u64);
+
+    #[test]
+    fn test_typed_store() {
+        let mut db = TypedStore::new();
+        let mut changes = db.subscribe();
+
+        assert_eq!(db.insert("rss/1", Interval(60)), None);
+        assert_eq!(db.insert("rss/1", Interval(300)), Some(Interval(60)));
+        db.insert("", "settings".to_owned());
+        assert_eq!(db.get::<Interval>("rss/1"), Some(&Interval(300)));
+        assert_eq!(db.get::<Interval>("/rss/1/"), Some(&Interval(300)));
+        assert_eq!(db.get::<String>("rss/1"), None);
+        assert_eq!(db.get::<String>("").map(|s| s.as_str()), Some("settings"));
+        assert_eq!(db.child("rss/1").unwrap().path(), "rss/1");
+        assert_eq!(db.children().collect::<Vec<_>>(), vec!["rss"]);
+
+        assert_eq!(db.update("rss/1", |i: &mut Interval| i.0 *= 2), Some(()));
+        assert_eq!(db.remove::<Interval>("rss/1"), Some(Interval(600)));
+        assert_eq!(db.remove::<Interval>("rss/1"), None);
+        assert!(db.remove_child("rss/1").is_some());
+        assert!(db.child("rss/1").is_none());
+
+        let kinds: Vec<_> = std::iter::from_fn(|| changes.try_recv().ok())
+            .map(|change| (change.is::<Interval>(), change.path, change.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (true, "rss/1".to_owned(), ChangeKind::Insert),
+                (true, "rss/1".to_owned(), ChangeKind::Update),
+                (false, "".to_owned(), ChangeKind::Insert),
+                (true, "rss/1".to_owned(), ChangeKind::Update),
+                (true, "rss/1".to_owned(), ChangeKind::Remove),
+            ]
+        );
+    }
+}
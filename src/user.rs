@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// 用户角色，权限从低到高排列，高权限包含低权限的所有操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, TS)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// 只能查看和串流
+    Viewer,
+    /// 可以管理自己的订阅和下载
+    Member,
+    /// 可以修改配置和管理用户
+    Admin,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct User {
+    /// 登录密码的 Argon2id 哈希
+    pub password: String,
+    pub role: Role,
+}
+
+/// 发出请求的用户，由 `ApiHandler` 在认证后写入 `Depot`
+#[derive(Debug, Clone)]
+pub struct CurrentUser {
+    pub name: String,
+    pub role: Role,
+}
+
+impl CurrentUser {
+    /// 管理员可以修改所有订阅，其他用户只能修改自己创建的订阅
+    pub fn can_modify(&self, owner: Option<&str>) -> bool {
+        self.role == Role::Admin || owner == Some(self.name.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_permissions() {
+        assert!(Role::Admin > Role::Member && Role::Member > Role::Viewer);
+        let member = CurrentUser {
+            name: "alice".to_owned(),
+            role: Role::Member,
+        };
+        assert!(member.can_modify(Some("alice")));
+        assert!(!member.can_modify(Some("bob")));
+        assert!(!member.can_modify(None));
+        let admin = CurrentUser {
+            name: "admin".to_owned(),
+            role: Role::Admin,
+        };
+        assert!(admin.can_modify(None));
+    }
+}
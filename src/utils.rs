@@ -12,6 +12,14 @@ pub fn sha256(data: &str) -> String {
     format!("{:X}", hasher.finalize())
 }
 
+/// 比较两个字节串是否相等，耗时只与长度有关，用于比较密钥
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 pub fn rand_str(length: usize) -> String {
     let mut rng = rand::thread_rng();
     let possible_chars = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
@@ -57,13 +65,21 @@ where
 }
 #[cfg(test)]
 mod test {
-    use crate::utils::{rand_str, sha256};
+    use crate::utils::{constant_time_eq, rand_str, sha256};
 
     #[test]
     fn test_sha256() {
         println!("{}", sha256("aaaaa"));
     }
 
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"feed-key", b"feed-key"));
+        assert!(!constant_time_eq(b"feed-key", b"feed-kez"));
+        assert!(!constant_time_eq(b"feed-key", b"feed"));
+        assert!(constant_time_eq(b"", b""));
+    }
+
     #[test]
     fn test_rand_str() {
         println!("{}", rand_str(8));
@@ -2,6 +2,10 @@ import { defineConfig } from "@rsbuild/core";
 import Components from "unplugin-vue-components/rspack";
 import { pluginVue } from "@rsbuild/plugin-vue";
 import { AntDesignVueResolver } from "unplugin-vue-components/resolvers";
+import CompressionPlugin from "compression-webpack-plugin";
+
+// 预压缩的静态文件，由服务端根据 Accept-Encoding 选择
+const compressed = /\.(js|css|html|svg|json)$/;
 
 export default defineConfig({
   plugins: [pluginVue()],
@@ -15,6 +19,18 @@ export default defineConfig({
             }),
           ],
         }),
+        new CompressionPlugin({
+          filename: "[path][base].br",
+          algorithm: "brotliCompress",
+          test: compressed,
+          threshold: 1024,
+        }),
+        new CompressionPlugin({
+          filename: "[path][base].gz",
+          algorithm: "gzip",
+          test: compressed,
+          threshold: 1024,
+        }),
       ],
     },
   },
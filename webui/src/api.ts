@@ -1,6 +1,4 @@
 import axios, { Axios, Method } from "axios";
-import { Sha256 } from "@aws-crypto/sha256-browser";
-import { bytesToHex } from "./utils";
 
 type ApiResponse = {
   code: number;
@@ -44,17 +42,80 @@ export class Api {
   }
 
   async login(username: string, password: string): Promise<string> {
-    const hash = new Sha256();
-    hash.update(password, "utf8");
     this.token = (
       await this.reqBase("login", {
         username: username,
-        password: bytesToHex(await hash.digest()).toUpperCase(),
+        password: password,
       })
     ).data.token;
     return this.token;
   }
 
+  async change_password(
+    current_password: string,
+    new_password: string
+  ): Promise<ApiResponse> {
+    return await this.reqBase("change_password", {
+      current_password: current_password,
+      new_password: new_password,
+    });
+  }
+
+  async logout(): Promise<ApiResponse> {
+    const resp = await this.reqBase("logout", {});
+    this.token = null;
+    return resp;
+  }
+
+  async logout_all(): Promise<ApiResponse> {
+    const resp = await this.reqBase("logout_all", {});
+    this.token = null;
+    return resp;
+  }
+
+  async get_session_list(): Promise<ApiResponse> {
+    return await this.reqBase("get_session_list", {}, "get");
+  }
+
+  async revoke_session(id: string): Promise<ApiResponse> {
+    return await this.reqBase("revoke_session", { id: id });
+  }
+
+  async get_api_key_list(): Promise<ApiResponse> {
+    return await this.reqBase("get_api_key_list", {}, "get");
+  }
+
+  async create_api_key(
+    name: string,
+    scopes: ("read" | "feeds" | "downloads")[] = []
+  ): Promise<ApiResponse> {
+    return await this.reqBase("create_api_key", { name: name, scopes: scopes });
+  }
+
+  async revoke_api_key(id: string): Promise<ApiResponse> {
+    return await this.reqBase("revoke_api_key", { id: id });
+  }
+
+  async get_user_list(): Promise<ApiResponse> {
+    return await this.reqBase("get_user_list", {}, "get");
+  }
+
+  async set_user(
+    username: string,
+    role: "admin" | "member" | "viewer",
+    password?: string
+  ): Promise<ApiResponse> {
+    return await this.reqBase("set_user", {
+      username: username,
+      role: role,
+      password: password,
+    });
+  }
+
+  async remove_user(username: string): Promise<ApiResponse> {
+    return await this.reqBase("remove_user", { username: username });
+  }
+
   async add_rss_sub(url: string): Promise<ApiResponse> {
     return await this.reqBase("add_rss_sub", { url: url });
   }
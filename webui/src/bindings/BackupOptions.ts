@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 配置文件和数据库的备份设置
+ */
+export type BackupOptions = { 
+/**
+ * 保留的备份数量，为 0 时不备份
+ */
+count: number, 
+/**
+ * 数据库备份的间隔，单位为秒
+ */
+interval: bigint, };
@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SeedingPolicy } from "./SeedingPolicy";
+
+/**
+ * 订阅分类，同一分类的订阅共享下载目录、做种策略、tracker 和优先级
+ */
+export type Category = { 
+/**
+ * 下载目录，为空时使用全局的 `output_path`
+ */
+output_path: string | null, seeding: SeedingPolicy, 
+/**
+ * 额外的 tracker
+ */
+trackers: Array<string>, 
+/**
+ * 优先级，数值越大越先获取元数据
+ */
+priority: number, };
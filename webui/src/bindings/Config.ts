@@ -1,4 +1,50 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BackupOptions } from "./BackupOptions";
+import type { Category } from "./Category";
+import type { MetadataOptions } from "./MetadataOptions";
+import type { SessionOptions } from "./SessionOptions";
+import type { TlsOptions } from "./TlsOptions";
 import type { TorrentOptions } from "./TorrentOptions";
+import type { User } from "./User";
 
-export type Config = { bind_address: string, password: string, username: string, token: string | null, db_path: string, session_path: string, torrent_options: TorrentOptions, output_path: string, };
+export type Config = { 
+/**
+ * 监听地址，多个地址用逗号分隔，`unix:<path>` 为 Unix 域套接字
+ */
+bind_address: string, 
+/**
+ * 登录密码的 Argon2id 哈希，旧版本的 SHA-256 哈希会在登录时升级
+ */
+password: string, 
+/**
+ * 内置账户的用户名，该账户始终为管理员
+ */
+username: string, token: string | null, db_path: string, session_path: string, torrent_options: TorrentOptions, output_path: string, metadata_options: MetadataOptions, 
+/**
+ * 访问已完成下载订阅源所需的密钥
+ */
+feed_key: string | null, 
+/**
+ * 对外访问的地址，用于生成订阅源中的文件链接，为空时使用请求的 Host
+ */
+public_url: string | null, 
+/**
+ * 订阅分类，以分类名为键
+ */
+categories: { [key in string]?: Category }, backup_options: BackupOptions, 
+/**
+ * Unix 域套接字文件的权限，八进制，例如 `660`
+ */
+unix_socket_mode: string | null, 
+/**
+ * HTTPS 设置，为空时只使用 HTTP
+ */
+tls: TlsOptions | null, 
+/**
+ * 从该目录提供网页界面而不是使用内嵌的文件，用于前端开发
+ */
+webui_dir: string | null, session_options: SessionOptions, 
+/**
+ * 内置账户以外的用户，以用户名为键
+ */
+users: { [key in string]?: User }, };
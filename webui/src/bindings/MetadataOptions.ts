@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type MetadataOptions = { 
+/**
+ * 同时获取元数据的最大数量
+ */
+concurrency: number, 
+/**
+ * 单次获取的超时时间，单位为秒
+ */
+timeout: bigint, 
+/**
+ * 失败后的最大重试次数
+ */
+max_retries: number, 
+/**
+ * 首次重试前的等待时间，单位为秒，之后每次翻倍
+ */
+retry_backoff: bigint, };
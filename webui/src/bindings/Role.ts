@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 用户角色，权限从低到高排列，高权限包含低权限的所有操作
+ */
+export type Role = /**
+ * 只能查看和串流
+ */
+"viewer" | /**
+ * 可以管理自己的订阅和下载
+ */
+"member" | /**
+ * 可以修改配置和管理用户
+ */
+"admin";
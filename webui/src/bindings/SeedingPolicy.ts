@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 下载完成后的做种策略，满足任一条件即停止做种，都为空时一直做种
+ */
+export type SeedingPolicy = { 
+/**
+ * 分享率上限
+ */
+ratio_limit: number | null, 
+/**
+ * 做种时间上限，单位为秒
+ */
+seed_time: bigint | null, };
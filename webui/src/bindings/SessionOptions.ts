@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * 登录会话设置，时间单位为秒，为 0 时不限制
+ */
+export type SessionOptions = { 
+/**
+ * 超过该时间没有请求的会话失效
+ */
+idle_timeout: bigint, 
+/**
+ * 会话从登录起的最长有效时间
+ */
+max_age: bigint, 
+/**
+ * 将会话保存到数据库，重启后无需重新登录
+ */
+persist: boolean, };
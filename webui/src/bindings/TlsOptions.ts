@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * HTTPS 设置，只用于 TCP 监听器
+ */
+export type TlsOptions = { 
+/**
+ * PEM 格式的证书链
+ */
+cert_path: string, 
+/**
+ * PEM 格式的私钥
+ */
+key_path: string, 
+/**
+ * 证书文件不存在时生成自签名证书
+ */
+self_signed: boolean, };
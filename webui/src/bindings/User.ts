@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Role } from "./Role";
+
+export type User = { 
+/**
+ * 登录密码的 Argon2id 哈希
+ */
+password: string, role: Role, };